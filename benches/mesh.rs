@@ -0,0 +1,52 @@
+//! Times `create_mesh_from_image` on generated images of a few sizes, to catch performance regressions.
+//!
+//! Run with `cargo bench`. Every size is meshed a few times to warm up the caches and the allocator, and then a
+//! number of times more, of which the fastest and median times are printed. This is a plain loop rather than a
+//! criterion bench, so the crate keeps building without fetching criterion's dependencies; the median is steady
+//! enough to spot the regressions this is for.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use image_to_mesh::{create_mesh_from_image, Params};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SIZES: &[u32] = &[64, 256, 1024];
+const WARMUP_RUNS: usize = 2;
+const RUNS: usize = 10;
+
+/// A blob with a wavy edge and a hole, like a sprite, scaled to fill most of a `size` by `size` image.
+fn sprite(size: u32) -> DynamicImage {
+    let center = size as f32 / 2.0;
+    let image = RgbaImage::from_fn(size, size, |x, y| {
+        let (dx, dy) = (x as f32 + 0.5 - center, y as f32 + 0.5 - center);
+        let (r, angle) = ((dx * dx + dy * dy).sqrt() / center, dy.atan2(dx));
+        let outer = 0.8 + 0.1 * (5.0 * angle).sin();
+        match r < outer && r > 0.2 {
+            true => Rgba([200, 120, 40, 255]),
+            false => Rgba([0, 0, 0, 0]),
+        }
+    });
+    DynamicImage::ImageRgba8(image)
+}
+
+fn main() {
+    for &size in SIZES {
+        let image = sprite(size);
+        for _ in 0..WARMUP_RUNS {
+            black_box(create_mesh_from_image(black_box(&image), Params::default()).expect("the sprite can be meshed"));
+        }
+
+        let mut times: Vec<Duration> = (0..RUNS).map(|_| {
+            let start = Instant::now();
+            black_box(create_mesh_from_image(black_box(&image), Params::default()).expect("the sprite can be meshed"));
+            start.elapsed()
+        }).collect();
+        times.sort();
+
+        println!(
+            "{size}x{size}: fastest {:.2} ms, median {:.2} ms",
+            times[0].as_secs_f64() * 1000.0,
+            times[RUNS / 2].as_secs_f64() * 1000.0
+        );
+    }
+}
@@ -1,6 +1,99 @@
 use std::{f32::consts::PI, ops::Index};
 use image::{DynamicImage, GenericImageView, GrayImage, ImageBuffer, Luma, Pixel};
 use sdfer::{Image2d, Unorm8};
+use crate::{marching_squares, MeshError, Progress};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// The size in pixels of the regions `Params::adaptive_threshold` estimates the edge level in.
+const ADAPTIVE_TILE_SIZE: u32 = 16;
+
+/// A grid of values that contours are traced in, such as a grayscale image or a distance field.
+/// Pixels with a value above the threshold are part of the shape.
+pub trait ThresholdField {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+    /// The value of the pixel at `(x, y)`, which lies inside the width and height of the field.
+    fn value_at(&self, x: u32, y: u32) -> u8;
+}
+
+impl ThresholdField for GrayImage {
+    fn width(&self) -> u32 {
+        ImageBuffer::width(self)
+    }
+
+    fn height(&self) -> u32 {
+        ImageBuffer::height(self)
+    }
+
+    fn value_at(&self, x: u32, y: u32) -> u8 {
+        self.get_pixel(x, y)[0]
+    }
+}
+
+/// Cells for which `is_inside` returns true are 255, the others 0.
+struct InsideFn<F: Fn(i32, i32) -> bool> {
+    width: u32,
+    height: u32,
+    is_inside: F,
+}
+
+impl<F: Fn(i32, i32) -> bool> ThresholdField for InsideFn<F> {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn value_at(&self, x: u32, y: u32) -> u8 {
+        if (self.is_inside)(x as i32, y as i32) { 255 } else { 0 }
+    }
+}
+
+/// The distance field computed by sdfer, traced directly instead of being copied into a `GrayImage` first.
+struct DistanceField {
+    sdf: Image2d<Unorm8, Vec<Unorm8>>,
+    width: u32,
+    height: u32,
+}
+
+impl DistanceField {
+    fn to_image(&self) -> GrayImage {
+        let mut imgbuf = GrayImage::new(self.width, self.height);
+
+        // One row of the output per chunk. The width is at least one, as chunks can't be empty.
+        let row_length = (self.width as usize).max(1);
+
+        #[cfg(feature = "parallel")]
+        let rows = (*imgbuf).par_chunks_mut(row_length);
+        #[cfg(not(feature = "parallel"))]
+        let rows = (*imgbuf).chunks_mut(row_length);
+
+        rows.enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = self.sdf[(x, y)].to_bits();
+            }
+        });
+
+        imgbuf
+    }
+}
+
+impl ThresholdField for DistanceField {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn value_at(&self, x: u32, y: u32) -> u8 {
+        self.sdf[(x as usize, y as usize)].to_bits()
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum LookDirection {
@@ -10,7 +103,20 @@ enum LookDirection {
     Up
 }
 
-#[derive(Debug, Clone)]
+/// A closed loop of points, without the first point repeated at the end.
+///
+/// The points are in one of two coordinate spaces, which the contour doesn't record itself:
+///
+/// * Pixels, for contours traced by `find_contour_from_grayscale` and the other functions taking a
+///   `ThresholdField`. The point (x, y) lies at pixel (x, y) of the traced image, which for a distance field from
+///   `compute_sdf` includes the `Params::pad_border` pixels of padding.
+/// * The [0, 1] image space, where the image spans [0, 1] on both axes, for contours returned by the
+///   `_from_transparency_with_offset` and `_from_mask` functions. The mesh functions expect this space.
+///
+/// `scale` takes a contour from pixels to the [0, 1] image space, and `process` applies all the steps those
+/// functions apply to a traced contour, in the same order. Distances given to the methods, like the `epsilon` of
+/// `simplify_rdp` or the `spacing` of `resample`, are in the units of the contour's own space.
+#[derive(Debug, Clone, Default)]
 pub struct Contour(Vec<[f32; 2]>);
 
 impl Index<usize> for Contour {
@@ -27,11 +133,24 @@ impl FromIterator<[f32; 2]> for Contour {
     }
 }
 
+impl IntoIterator for Contour {
+    type Item = [f32; 2];
+    type IntoIter = std::vec::IntoIter<[f32; 2]>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
 impl Contour {
     pub fn len(&self) -> usize {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn push(&mut self, point: [f32; 2]) {
         self.0.push(point);
     }
@@ -40,42 +159,175 @@ impl Contour {
         self.0.iter()
     }
 
+    /// Returns the points of the contour as a slice.
+    pub fn as_slice(&self) -> &[[f32; 2]] {
+        &self.0
+    }
+
+    /// Consumes the contour and returns its points.
+    pub fn into_points(self) -> Vec<[f32; 2]> {
+        self.0
+    }
+
     pub fn new() -> Contour {
         Contour(vec![])
     }
 
-    pub fn into_iter(self) -> std::vec::IntoIter<[f32; 2]> {
-        self.0.into_iter()
+    /// Creates a contour from points produced elsewhere, e.g. by another segmentation, checking what the rest of
+    /// the crate assumes of a contour: it has at least 3 points, and every coordinate is finite. The points are
+    /// taken as a closed loop, without repeating the first point at the end.
+    ///
+    /// # Returns
+    ///
+    /// The contour, or `MeshError::InvalidPolygon` if there are fewer than 3 points or a coordinate is NaN or infinite.
+    pub fn from_points(points: Vec<[f32; 2]>) -> Result<Contour, MeshError> {
+        if points.len() < 3 || points.iter().flatten().any(|c| !c.is_finite()) {
+            return Err(MeshError::InvalidPolygon);
+        }
+        Ok(Contour(points))
     }
 
+    /// Moves every point halfway towards the midpoint of its two neighbours, `iterations` times. Shrinks the
+    /// shape slightly. Smoothing is the same in pixels and in the [0, 1] image space, as it only averages points.
     pub fn smooth(self, iterations: u32) -> Contour {
-        let mut smoothed_contour:Contour = self.clone();
+        let mut smoothed_contour = self.0;
+        // Every iteration writes into the other buffer and then swaps them, so only two vectors are ever allocated.
+        let mut curr_smoothed_contour: Vec<[f32; 2]> = Vec::with_capacity(smoothed_contour.len());
     
         for _ in 0..iterations {
-            // Could technically swap between self and smoothed_contour to avoid creating a new vector every iteration.
-            // Won't bother to do it though.
-            let mut curr_smoothed_contour:Contour = Contour::new();
+            let n_points = smoothed_contour.len();
+            curr_smoothed_contour.clear();
     
-            for i in 0..smoothed_contour.len() {
-                let prev = smoothed_contour[(i + smoothed_contour.len() - 1) % smoothed_contour.len()];
+            for i in 0..n_points {
+                let prev = smoothed_contour[(i + n_points - 1) % n_points];
                 let current = smoothed_contour[i];
-                let next = smoothed_contour[(i + 1) % smoothed_contour.len()];
+                let next = smoothed_contour[(i + 1) % n_points];
         
                 let smoothed = [(prev[0] + current[0] + next[0]) / 3.0, (prev[1] + current[1] + next[1]) / 3.0];
                 curr_smoothed_contour.push(smoothed);
             }
-            smoothed_contour = curr_smoothed_contour;
+            std::mem::swap(&mut smoothed_contour, &mut curr_smoothed_contour);
         }
     
+        Contour(smoothed_contour)
+    }
+
+    /// Same as `smooth`, but leaves sharp corners untouched.
+    ///
+    /// A point is only averaged with its neighbours if the contour turns by less than `angle_threshold`
+    /// radians at it, measured like in `simplify`. The angles are measured again before every iteration.
+    pub fn smooth_preserving(self, iterations: u32, angle_threshold: f32) -> Contour {
+        let mut smoothed_contour = self.0;
+        let mut curr_smoothed_contour: Vec<[f32; 2]> = Vec::with_capacity(smoothed_contour.len());
+
+        for _ in 0..iterations {
+            let n_points = smoothed_contour.len();
+            curr_smoothed_contour.clear();
+
+            for i in 0..n_points {
+                let prev = smoothed_contour[(i + n_points - 1) % n_points];
+                let current = smoothed_contour[i];
+                let next = smoothed_contour[(i + 1) % n_points];
+
+                let v0 = normalize(sub(next, current));
+                let v1 = normalize(sub(prev, current));
+                let turning_angle = PI - (v0[0] * v1[0] + v0[1] * v1[1]).clamp(-1.0, 1.0).acos();
+
+                if turning_angle > angle_threshold {
+                    curr_smoothed_contour.push(current);
+                    continue;
+                }
+
+                let smoothed = [(prev[0] + current[0] + next[0]) / 3.0, (prev[1] + current[1] + next[1]) / 3.0];
+                curr_smoothed_contour.push(smoothed);
+            }
+            std::mem::swap(&mut smoothed_contour, &mut curr_smoothed_contour);
+        }
+
+        Contour(smoothed_contour)
+    }
+
+    /// Smooths the contour with Chaikin's corner cutting.
+    ///
+    /// Every iteration replaces each edge with two points, at 1/4 and 3/4 along the edge,
+    /// so a contour of `n` points ends up with `n * 2^iterations` points.
+    /// Unlike `smooth`, the result stays close to the original outline.
+    pub fn smooth_chaikin(self, iterations: u32) -> Contour {
+        let mut smoothed_contour = self;
+
+        for _ in 0..iterations {
+            let n_points = smoothed_contour.len();
+            let mut curr_smoothed_contour = Contour(Vec::with_capacity(2 * n_points));
+
+            for i in 0..n_points {
+                let current = smoothed_contour[i];
+                let next = smoothed_contour[(i + 1) % n_points];
+
+                curr_smoothed_contour.push([0.75 * current[0] + 0.25 * next[0], 0.75 * current[1] + 0.25 * next[1]]);
+                curr_smoothed_contour.push([0.25 * current[0] + 0.75 * next[0], 0.25 * current[1] + 0.75 * next[1]]);
+            }
+            smoothed_contour = curr_smoothed_contour;
+        }
+
         smoothed_contour
     }
-    
+
+    /// Smooths the contour with Taubin's λ|μ smoothing.
+    ///
+    /// Every iteration moves each point `lambda` of the way towards the midpoint of its neighbours, which
+    /// shrinks the shape like `smooth`, and then `mu` of the way, where `mu` is negative and slightly larger
+    /// in magnitude than `lambda`, which grows it back. Noise is removed while the size of the shape is kept.
+    /// `lambda = 0.5` and `mu = -0.53` work well.
+    pub fn smooth_taubin(self, iterations: u32, lambda: f32, mu: f32) -> Contour {
+        let mut smoothed_contour = self.0;
+        let mut curr_smoothed_contour: Vec<[f32; 2]> = Vec::with_capacity(smoothed_contour.len());
+
+        for _ in 0..iterations {
+            for factor in [lambda, mu] {
+                let n_points = smoothed_contour.len();
+                curr_smoothed_contour.clear();
+
+                for i in 0..n_points {
+                    let prev = smoothed_contour[(i + n_points - 1) % n_points];
+                    let current = smoothed_contour[i];
+                    let next = smoothed_contour[(i + 1) % n_points];
+
+                    let midpoint = [(prev[0] + next[0]) / 2.0, (prev[1] + next[1]) / 2.0];
+                    curr_smoothed_contour.push([
+                        current[0] + factor * (midpoint[0] - current[0]),
+                        current[1] + factor * (midpoint[1] - current[1]),
+                    ]);
+                }
+                std::mem::swap(&mut smoothed_contour, &mut curr_smoothed_contour);
+            }
+        }
+
+        Contour(smoothed_contour)
+    }
+
+    /// Smooths the contour with the given mode.
+    pub fn smooth_with(self, mode: SmoothMode, iterations: u32) -> Contour {
+        match mode {
+            SmoothMode::Average => self.smooth(iterations),
+            SmoothMode::Chaikin => self.smooth_chaikin(iterations),
+            SmoothMode::Taubin { lambda, mu } => self.smooth_taubin(iterations, lambda, mu),
+        }
+    }
+
+    /// Removes the points where the contour turns by less than `comparison_angle` radians, i.e. the points along
+    /// nearly straight stretches. The angles are measured in the contour's own space, so for an image that isn't
+    /// square, the same contour loses slightly different points in pixels than in the [0, 1] image space, where
+    /// `process` simplifies it. Contours of fewer than 3 points are returned unchanged.
     pub fn simplify(
         self, 
         comparison_angle: f32)
         -> Contour {
     
         let n_points = self.len();
+        if n_points < 3 {
+            return self;
+        }
+
         let mut should_be_deleted: Vec<bool> = vec![false; n_points];
         let mut current_prev_point = self[n_points - 1];
         for i in 0..n_points {
@@ -96,21 +348,365 @@ impl Contour {
     
         self.into_iter()
         .enumerate()
-        .filter(|(i, _)| should_be_deleted[*i])
+        .filter(|(i, _)| !should_be_deleted[*i])
         .map(|(_, p)| p)
         .collect()
     }
     
+    /// Simplifies the contour with the Ramer–Douglas–Peucker algorithm.
+    ///
+    /// No point of the original contour is further than `epsilon` from the simplified contour.
+    /// As the contour is closed, it's split into two open polylines at the first point and the point
+    /// furthest away from it, which are then simplified separately. Both of these points are always kept.
+    pub fn simplify_rdp(self, epsilon: f32) -> Contour {
+        let n_points = self.len();
+        if n_points < 4 {
+            return self;
+        }
+
+        let first = self[0];
+        let furthest = (1..n_points)
+            .max_by(|a, b| length(sub(self[*a], first)).total_cmp(&length(sub(self[*b], first))))
+            .unwrap();
+
+        let mut keep = vec![false; n_points];
+        keep[0] = true;
+        keep[furthest] = true;
+
+        let first_half: Vec<usize> = (0..=furthest).collect();
+        let second_half: Vec<usize> = (furthest..n_points).chain([0]).collect();
+        rdp_mark(&self.0, &first_half, epsilon, &mut keep);
+        rdp_mark(&self.0, &second_half, epsilon, &mut keep);
+
+        self.into_iter()
+        .enumerate()
+        .filter(|(i, _)| keep[*i])
+        .map(|(_, p)| p)
+        .collect()
+    }
+
+    /// Simplifies the contour with the given mode.
+    pub fn simplify_with(self, mode: SimplifyMode) -> Contour {
+        match mode {
+            SimplifyMode::Angle(angle) => self.simplify(angle),
+            SimplifyMode::Rdp(epsilon) => self.simplify_rdp(epsilon),
+        }
+    }
+
+    /// Places points at equal distances along the closed contour, starting at the first point, so the side walls
+    /// get quads of the same width.
+    ///
+    /// The spacing is adjusted so a whole number of points, at least 3, fits into the perimeter, which keeps the
+    /// gap between the last and the first point the same as the others. Corners between the new points are cut off.
+    pub fn resample(self, spacing: f32) -> Contour {
+        let n_points = self.len();
+        let perimeter = self.perimeter();
+        if n_points < 2 || spacing <= 0.0 || perimeter <= 0.0 {
+            return self;
+        }
+
+        let n_resampled = ((perimeter / spacing).round() as usize).max(3);
+        let step = perimeter / n_resampled as f32;
+        let mut resampled = Contour(Vec::with_capacity(n_resampled));
+
+        // The edge from point `i` to the next one, and the distance along the contour to point `i`.
+        let (mut i, mut walked) = (0, 0.0);
+        for k in 0..n_resampled {
+            let target = k as f32 * step;
+            loop {
+                let (start, end) = (self[i], self[(i + 1) % n_points]);
+                let edge_length = length(sub(end, start));
+                if walked + edge_length < target && i < n_points - 1 {
+                    walked += edge_length;
+                    i += 1;
+                    continue;
+                }
+
+                let t = if edge_length > 0.0 { ((target - walked) / edge_length).clamp(0.0, 1.0) } else { 0.0 };
+                resampled.push([start[0] + t * (end[0] - start[0]), start[1] + t * (end[1] - start[1])]);
+                break;
+            }
+        }
+
+        resampled
+    }
+
+    /// Removes points closer than `epsilon` to the point before them, including the last point if it's that close
+    /// to the first one.
+    pub fn dedup(self, epsilon: f32) -> Contour {
+        let mut points: Vec<[f32; 2]> = Vec::with_capacity(self.len());
+        for p in self {
+            if points.last().is_none_or(|last| length(sub(p, *last)) >= epsilon) {
+                points.push(p);
+            }
+        }
+
+        while points.len() > 1 && length(sub(points[points.len() - 1], points[0])) < epsilon {
+            points.pop();
+        }
+
+        Contour(points)
+    }
+
+    /// Moves every point by `offset` along both axes, e.g. by `-pad_border` to undo the padding of `compute_sdf`.
+    pub fn translate(self, offset: f32) -> Contour {
+        self.into_iter().map(|p| [p[0] + offset, p[1] + offset]).collect()
+    }
+
+    /// Divides the x coordinates by `width` and the y coordinates by `height`, which takes a contour traced in an
+    /// image of `width` by `height` pixels to the [0, 1] image space. A contour already in that space would shrink.
     pub fn scale(self, width: f32, height: f32) -> Contour {
         self.into_iter().map(|p| [p[0] / width, p[1] / height]).collect()
     }
 
+    /// Takes a contour traced in a distance field from `compute_sdf` through the steps the
+    /// `_from_transparency_with_offset` functions take after tracing, in the same order: it's moved back by
+    /// `params.pad_border` and smoothed with `params.smooth_mode` in pixels, scaled to the [0, 1] image space of an
+    /// image of `width` by `height` pixels, simplified with `params.simplify_mode`, and resampled if
+    /// `params.resample_spacing` is set. Contours smaller than `params.min_area` are not dropped.
+    pub fn process(self, params: &Params, width: u32, height: u32) -> Contour {
+        let contour = self
+            .translate(-(params.pad_border as f32))
+            .smooth_with(params.smooth_mode, params.smooth_iterations)
+            .scale(width as f32, height as f32)
+            .simplify_with(params.simplify_mode);
+        match params.resample_spacing {
+            Some(spacing) => contour.resample(spacing),
+            None => contour,
+        }
+    }
+
+    /// Whether the contour winds clockwise, measured with the y axis pointing up like the shoelace formula.
+    /// Contours are in image coordinates with the y axis pointing down, where a clockwise contour looks counter-clockwise.
+    pub fn is_clockwise(&self) -> bool {
+        signed_area(self) < 0.0
+    }
+
+    /// Reverses the contour if it winds clockwise, see `is_clockwise`.
+    pub fn ensure_ccw(self) -> Contour {
+        if self.is_clockwise() { self.into_iter().rev().collect() } else { self }
+    }
+
+    /// Reverses the contour if it winds counter-clockwise, see `is_clockwise`.
+    pub fn ensure_cw(self) -> Contour {
+        if signed_area(&self) > 0.0 { self.into_iter().rev().collect() } else { self }
+    }
+
+    /// The area enclosed by the contour, regardless of its winding, in the square units of the contour's space.
+    /// In the [0, 1] image space, it's the fraction of the image the contour encloses, like `Params::min_area`.
+    pub fn area(&self) -> f32 {
+        signed_area(self).abs()
+    }
+
+    /// Whether the contour is a simple polygon: it has at least three points, no edge crosses or touches an edge
+    /// other than its neighbours, and no edge doubles back over the one before it.
+    pub fn is_simple(&self) -> bool {
+        self.len() >= 3 && self.first_intersection().is_none()
+    }
+
+    /// The start points of the first two edges found that cross or touch, ordered by index. The edges are swept
+    /// from left to right, so only edges that overlap along x are compared.
+    fn first_intersection(&self) -> Option<(usize, usize)> {
+        let n_points = self.len();
+        let edge = |i: usize| (self[i], self[(i + 1) % n_points]);
+        let min_x = |i: usize| { let (a, b) = edge(i); a[0].min(b[0]) };
+        let max_x = |i: usize| { let (a, b) = edge(i); a[0].max(b[0]) };
+
+        let mut order: Vec<usize> = (0..n_points).collect();
+        order.sort_by(|a, b| min_x(*a).total_cmp(&min_x(*b)));
+
+        let mut active: Vec<usize> = vec![];
+        for i in order {
+            active.retain(|j| max_x(*j) >= min_x(i));
+            let (a, b) = edge(i);
+            for &j in &active {
+                let (c, d) = edge(j);
+                let intersects = if j == (i + 1) % n_points {
+                    folds_back(a, b, d)
+                } else if i == (j + 1) % n_points {
+                    folds_back(c, d, b)
+                } else {
+                    segments_intersect(a, b, c, d)
+                };
+                if intersects {
+                    return Some((i.min(j), i.max(j)));
+                }
+            }
+            active.push(i);
+        }
+        None
+    }
+
+    /// The length of the closed contour, including the edge from the last point back to the first.
+    pub fn perimeter(&self) -> f32 {
+        let n_points = self.len();
+        (0..n_points).map(|i| length(sub(self[(i + 1) % n_points], self[i]))).sum()
+    }
+
+    /// The center of mass of the enclosed area. Falls back to the average of the points if the area is zero,
+    /// and returns the origin for an empty contour.
+    pub fn centroid(&self) -> [f32; 2] {
+        let n_points = self.len();
+        if n_points == 0 {
+            return [0.0, 0.0];
+        }
+
+        let area = signed_area(self);
+        if area.abs() <= f32::EPSILON {
+            let sum = self.iter().fold([0.0, 0.0], |sum, p| [sum[0] + p[0], sum[1] + p[1]]);
+            return [sum[0] / n_points as f32, sum[1] / n_points as f32];
+        }
+
+        let mut centroid = [0.0, 0.0];
+        for i in 0..n_points {
+            let p0 = self[i];
+            let p1 = self[(i + 1) % n_points];
+            let cross = p0[0] * p1[1] - p1[0] * p0[1];
+            centroid[0] += (p0[0] + p1[0]) * cross;
+            centroid[1] += (p0[1] + p1[1]) * cross;
+        }
+
+        [centroid[0] / (6.0 * area), centroid[1] / (6.0 * area)]
+    }
+
+}
+
+/// The outer contour of a shape together with the contours of its holes.
+#[derive(Debug, Clone)]
+pub struct Outline {
+    pub outer: Contour,
+    pub holes: Vec<Contour>,
+}
+
+impl Outline {
+    /// Applies `f` to the outer contour and to every hole.
+    pub fn map(self, f: impl Fn(Contour) -> Contour) -> Outline {
+        Outline {
+            outer: f(self.outer),
+            holes: self.holes.into_iter().map(&f).collect(),
+        }
+    }
+
+    /// Iterates over the outer contour followed by the holes.
+    pub fn iter(&self) -> impl Iterator<Item = &Contour> {
+        std::iter::once(&self.outer).chain(self.holes.iter())
+    }
+}
+
+/// Which part of the image defines the shape.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MaskSource {
+    /// Opaque pixels are part of the shape.
+    #[default]
+    Alpha,
+    /// Bright pixels are part of the shape.
+    Luminance,
+    /// Pixels within `tolerance` of the key color on every channel are background, all others are part of the shape.
+    ColorKey { rgb: [u8; 3], tolerance: u8 },
+}
+
+/// How a contour is smoothed after tracing.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SmoothMode {
+    /// `Contour::smooth`, averaging every point with its neighbours. Shrinks the shape slightly.
+    #[default]
+    Average,
+    /// `Contour::smooth_chaikin`, doubling the number of points per iteration.
+    Chaikin,
+    /// `Contour::smooth_taubin` with the given factors. Keeps the size of the shape.
+    Taubin { lambda: f32, mu: f32 },
+}
+
+/// How a contour is simplified after smoothing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimplifyMode {
+    /// `Contour::simplify` with the given angle, in radians.
+    Angle(f32),
+    /// `Contour::simplify_rdp` with the given maximum distance, in the [0, 1] image space.
+    Rdp(f32),
+}
+
+impl Default for SimplifyMode {
+    fn default() -> Self {
+        SimplifyMode::Angle(PI / 30.0)
+    }
+}
+
+/// Which algorithm finds the boundary of the shape in the distance field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContourAlgorithm {
+    /// Walks along the boundary pixel by pixel, see `find_outline_from_grayscale`.
+    #[default]
+    SquareTracing,
+    /// Marching squares, see `find_contours_marching_squares`.
+    /// Handles thin diagonal features and shapes touching at a corner.
+    MarchingSquares,
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct Params {
+    /// The radius of the distance field, in pixels. Larger values smooth over noise and jagged edges at the cost of
+    /// rounding off small details, and move the contour outward, see `sdf_cutoff`. 10 to 40 suits most images.
     pub border_offset: f32,
+    /// How often the contour is smoothed with `smooth_mode` before it's simplified. 0 keeps the staircase of the
+    /// pixels, and 5 to 30 rounds it off, where `SmoothMode::Average` shrinks the shape a little more every time.
     pub smooth_iterations: u32,
-    pub simplify_angle: f32,
+    pub smooth_mode: SmoothMode,
+    /// How points are dropped from the smoothed contour. For `SimplifyMode::Angle`, 1 to 15 degrees keeps the
+    /// shape recognizable, from dense contours at the low end to a few points per curve at the high end.
+    pub simplify_mode: SimplifyMode,
+    pub mask_source: MaskSource,
+    pub algorithm: ContourAlgorithm,
+    /// Transparent pixels added on every side of the image before tracing, so shapes touching the edge of the image
+    /// get a closed contour. `border_offset` grows the shape into this padding, so a padding of at least
+    /// `border_offset` keeps the grown shape from being cut off at the edge. The contour is given in the
+    /// coordinates of the unpadded image, so it can extend beyond [0, 1].
+    pub pad_border: u32,
+    /// Shifts the contour along the distance field, in units of `border_offset`.
+    ///
+    /// The distance field stores `255 * (1 - (d / border_offset + sdf_cutoff))` for a pixel at distance `d`
+    /// outside the shape (negative inside), and the contour is traced where it crosses `threshold`. With the
+    /// default threshold of 128 the contour lies about `border_offset * (0.5 - sdf_cutoff)` pixels outside the
+    /// original edge: the default of 0 grows the shape by half of `border_offset`, 0.5 follows the edge, and
+    /// larger values shrink the shape.
+    pub sdf_cutoff: f32,
+    /// The distance field value the contour is traced at. Lower values loosen the contour and higher values
+    /// tighten it, by `border_offset / 255` pixels per step.
+    pub threshold: u8,
+    /// The most steps the square tracing algorithm takes along a single contour before giving up with
+    /// `MeshError::ContourTooComplex`, bounding the time spent on pathological images. `None` allows
+    /// `(width + 2) * (height + 2)` steps for a distance field of `width` by `height` pixels, the pixels of the field
+    /// and the background ring around it.
+    /// Marching squares doesn't step along the contour, so it isn't limited.
+    pub max_trace_steps: Option<u32>,
+    /// When the square tracing algorithm runs out of steps on a contour, closes the contour it traced so far
+    /// instead of returning an error, and reports `Progress::ContourNotClosed`. If the tracer ended up going round
+    /// a loop, only one round of it is kept.
+    pub close_unclosed_contours: bool,
+    /// With a tolerance above 0, the square tracing algorithm also ends a contour when it gets within this many
+    /// pixels of where it started, after first having moved further away than that. This closes contours whose
+    /// tracer passes next to its starting point without going through it, which happens where the distance field
+    /// is noisy. It should stay well below the width of the thinnest part of the shape, or the tracer stops where
+    /// the other side of it passes the starting point. At 0, a contour only ends back at its starting point.
+    pub close_tolerance: f32,
+    /// Contours enclosing less than this area, as a fraction of the image area, are dropped after simplification,
+    /// so stray pixels don't become shapes or holes of their own. If no shape is left,
+    /// `MeshError::NoSignificantContour` is returned.
+    pub min_area: f32,
+    /// Moves the contour outward by this many pixels if positive, dilating the shape, or inward if negative,
+    /// eroding it. Unlike `border_offset`, this leaves the distance field as it is and only changes the value
+    /// the contour is traced at, see `trace_threshold`. The distance field only spans about `border_offset`
+    /// pixels, so larger offsets are cut off there.
+    pub mask_offset: f32,
+    /// Finds the edge of the shape at the coverage level where the coverage changes fastest, instead of halfway
+    /// between transparent and opaque. Crisp edges come out the same, while soft edges such as a glow or feathering
+    /// are traced where they visibly end rather than at an arbitrary alpha. The level is estimated per region of
+    /// `ADAPTIVE_TILE_SIZE` pixels, as the mean coverage weighted by the squared coverage gradient over the region
+    /// and the regions up to two away, and blended between neighbouring regions.
+    pub adaptive_threshold: bool,
+    /// Resamples the contour after simplification to points about this far apart, in the [0, 1] image space,
+    /// see `Contour::resample`. `None` keeps the points of the simplified contour.
+    pub resample_spacing: Option<f32>,
 }
 
 impl Default for Params {
@@ -118,70 +714,699 @@ impl Default for Params {
         Params {
             border_offset: 20.0,
             smooth_iterations: 10,
-            simplify_angle: PI/30.0,
+            smooth_mode: SmoothMode::default(),
+            simplify_mode: SimplifyMode::default(),
+            mask_source: MaskSource::default(),
+            algorithm: ContourAlgorithm::default(),
+            pad_border: 0,
+            sdf_cutoff: 0.0,
+            threshold: 128,
+            max_trace_steps: None,
+            close_unclosed_contours: false,
+            close_tolerance: 0.0,
+            min_area: 0.0,
+            mask_offset: 0.0,
+            adaptive_threshold: false,
+            resample_spacing: None,
+        }
+    }
+}
+
+impl Params {
+    /// Returns a builder starting from the default parameters.
+    pub fn builder() -> ParamsBuilder {
+        ParamsBuilder::default()
+    }
+
+    /// The distance field value the contour is traced at: `threshold`, lowered by `255 / border_offset`
+    /// per pixel of `mask_offset` to move the contour outward, or raised to move it inward. Without a positive
+    /// `border_offset`, the distance field has no pixels to move by, and `threshold` is used as is.
+    pub fn trace_threshold(&self) -> u8 {
+        if self.mask_offset == 0.0 || self.border_offset <= 0.0 {
+            return self.threshold;
         }
+        (self.threshold as f32 - 255.0 * self.mask_offset / self.border_offset).round().clamp(0.0, 255.0) as u8
+    }
+}
+
+/// Builder for `Params`, starting from `Params::default()`.
+#[derive(Default)]
+pub struct ParamsBuilder {
+    params: Params,
+}
+
+impl ParamsBuilder {
+    pub fn border_offset(mut self, border_offset: f32) -> Self {
+        self.params.border_offset = border_offset;
+        self
+    }
+
+    pub fn smooth_iterations(mut self, smooth_iterations: u32) -> Self {
+        self.params.smooth_iterations = smooth_iterations;
+        self
+    }
+
+    pub fn smooth_mode(mut self, smooth_mode: SmoothMode) -> Self {
+        self.params.smooth_mode = smooth_mode;
+        self
+    }
+
+    pub fn simplify_angle(mut self, simplify_angle: f32) -> Self {
+        self.params.simplify_mode = SimplifyMode::Angle(simplify_angle);
+        self
+    }
+
+    pub fn simplify_rdp(mut self, epsilon: f32) -> Self {
+        self.params.simplify_mode = SimplifyMode::Rdp(epsilon);
+        self
+    }
+
+    pub fn mask_source(mut self, mask_source: MaskSource) -> Self {
+        self.params.mask_source = mask_source;
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: ContourAlgorithm) -> Self {
+        self.params.algorithm = algorithm;
+        self
+    }
+
+    pub fn pad_border(mut self, pad_border: u32) -> Self {
+        self.params.pad_border = pad_border;
+        self
+    }
+
+    pub fn sdf_cutoff(mut self, sdf_cutoff: f32) -> Self {
+        self.params.sdf_cutoff = sdf_cutoff;
+        self
+    }
+
+    pub fn threshold(mut self, threshold: u8) -> Self {
+        self.params.threshold = threshold;
+        self
+    }
+
+    pub fn max_trace_steps(mut self, max_trace_steps: u32) -> Self {
+        self.params.max_trace_steps = Some(max_trace_steps);
+        self
+    }
+
+    pub fn close_unclosed_contours(mut self, close_unclosed_contours: bool) -> Self {
+        self.params.close_unclosed_contours = close_unclosed_contours;
+        self
+    }
+
+    pub fn close_tolerance(mut self, close_tolerance: f32) -> Self {
+        self.params.close_tolerance = close_tolerance;
+        self
+    }
+
+    pub fn min_area(mut self, min_area: f32) -> Self {
+        self.params.min_area = min_area;
+        self
+    }
+
+    pub fn mask_offset(mut self, mask_offset: f32) -> Self {
+        self.params.mask_offset = mask_offset;
+        self
+    }
+
+    pub fn adaptive_threshold(mut self, adaptive_threshold: bool) -> Self {
+        self.params.adaptive_threshold = adaptive_threshold;
+        self
+    }
+
+    pub fn resample_spacing(mut self, resample_spacing: f32) -> Self {
+        self.params.resample_spacing = Some(resample_spacing);
+        self
+    }
+
+    pub fn build(self) -> Params {
+        self.params
+    }
+}
+
+/// Computes the distance field that the contour of `img` is traced in, for inspecting it or tracing it directly
+/// with `find_contour_from_grayscale` and `params.trace_threshold()`.
+///
+/// The distance field is `params.pad_border` pixels larger than `img` on every side, and contours traced in it are
+/// in pixels of the padded image, not scaled to [0, 1] like the ones returned by the other functions.
+pub fn compute_sdf(img: &DynamicImage, params: &Params) -> GrayImage {
+    sdf_from_coverage(&mask_from_source(img, params.mask_source), params).to_image()
+}
+
+fn sdf_from_coverage(coverage: &GrayImage, params: &Params) -> DistanceField {
+    let padded = match params.adaptive_threshold {
+        true => pad_image(&adapt_coverage(coverage), params.pad_border),
+        false => pad_image(coverage, params.pad_border),
+    };
+    sdf_image(padded.width(), padded.height(), params.border_offset, params.sdf_cutoff, &padded)
+}
+
+pub fn find_contour_from_transparency_with_offset(img: &DynamicImage, params: Params) -> Result<Contour, MeshError> {
+    find_contour_from_coverage(&mask_from_source(img, params.mask_source), params)
+}
+
+/// Same as `find_contour_from_transparency_with_offset`, but also finds the holes inside the shape.
+pub fn find_outline_from_transparency_with_offset(img: &DynamicImage, params: Params) -> Result<Outline, MeshError> {
+    Ok(find_outlines_from_coverage(&mask_from_source(img, params.mask_source), params, false, &|_| {})?.remove(0))
+}
+
+/// Same as `find_outline_from_transparency_with_offset`, but finds the outline of every shape in the image.
+pub fn find_all_outlines_from_transparency_with_offset(img: &DynamicImage, params: Params) -> Result<Vec<Outline>, MeshError> {
+    find_outlines_from_coverage(&mask_from_source(img, params.mask_source), params, true, &|_| {})
+}
+
+/// Same as `find_outline_from_transparency_with_offset`, but the shape is given by a mask instead of an image.
+///
+/// Pixels above `threshold` are part of the shape. `params.mask_source` is not used.
+pub fn find_outline_from_mask(mask: &GrayImage, threshold: u8, params: Params) -> Result<Outline, MeshError> {
+    Ok(find_outlines_from_coverage(&binarize(mask, threshold), params, false, &|_| {})?.remove(0))
+}
+
+/// Same as `find_outline_from_mask`, but finds the outline of every shape in the mask.
+pub fn find_all_outlines_from_mask(mask: &GrayImage, threshold: u8, params: Params) -> Result<Vec<Outline>, MeshError> {
+    find_outlines_from_coverage(&binarize(mask, threshold), params, true, &|_| {})
+}
+
+fn find_contour_from_coverage(coverage: &GrayImage, params: Params) -> Result<Contour, MeshError> {
+
+    let (width, height) = coverage.dimensions();
+
+    check_coverage(coverage, &params)?;
+
+    let sdf = sdf_from_coverage(coverage, &params);
+
+    let contour = match params.algorithm {
+        ContourAlgorithm::SquareTracing => contour_from_grayscale(&sdf, params.trace_threshold(), &TraceOptions::from_params(&params, &|_| {}))?,
+        ContourAlgorithm::MarchingSquares => find_outline_marching_squares(&sdf, params.trace_threshold())?.outer,
+    };
+
+    let contour = contour.process(&params, width, height);
+
+    if contour.len() < 3 {
+        return Err(MeshError::InvalidPolygon);
+    }
+    if contour.area() < params.min_area {
+        return Err(MeshError::NoSignificantContour);
+    }
+    Ok(contour)
+}
+
+/// Returns `MeshError::FullyTransparent` or `MeshError::FullyOpaque` if the coverage is the same everywhere.
+/// The distance field has no edge to measure from then, and comes out as if the whole image were inside the shape.
+/// With `params.pad_border` set, the padding gives a fully opaque image an edge, so only `FullyTransparent` is
+/// returned.
+fn check_coverage(coverage: &GrayImage, params: &Params) -> Result<(), MeshError> {
+    let mut values = coverage.pixels().map(|pixel| pixel.0[0]);
+    let Some(first) = values.next() else { return Ok(()) };
+    if values.any(|value| value != first) {
+        return Ok(());
+    }
+
+    match (first >= 128, params.pad_border > 0) {
+        (true, true) => Ok(()),
+        (true, false) => Err(MeshError::FullyOpaque),
+        (false, _) => Err(MeshError::FullyTransparent),
+    }
+}
+
+/// Finds the outline of the first shape, or of every shape if `all_shapes` is set, in `coverage`.
+pub(crate) fn find_outlines_from_coverage(
+    coverage: &GrayImage,
+    params: Params,
+    all_shapes: bool,
+    progress: &dyn Fn(Progress))
+    -> Result<Vec<Outline>, MeshError> {
+
+    let (width, height) = coverage.dimensions();
+
+    check_coverage(coverage, &params)?;
+
+    progress(Progress::SdfStarted);
+    let sdf = sdf_from_coverage(coverage, &params);
+    progress(Progress::TracingStarted);
+
+    let (f_width, f_height) = (width as f32, height as f32);
+
+    let options = TraceOptions::from_params(&params, progress);
+    let mut outlines = match (params.algorithm, all_shapes) {
+        (ContourAlgorithm::SquareTracing, false) => vec![outline_from_grayscale(&sdf, params.trace_threshold(), &options)?],
+        (ContourAlgorithm::SquareTracing, true) => all_outlines_from_grayscale(&sdf, params.trace_threshold(), &options)?,
+        (ContourAlgorithm::MarchingSquares, false) => vec![find_outline_marching_squares(&sdf, params.trace_threshold())?],
+        (ContourAlgorithm::MarchingSquares, true) => outlines_from_loops(find_contours_marching_squares(&sdf, params.trace_threshold())?),
+    };
+    progress(Progress::ContourTraced { points: outlines.iter().flat_map(|o| o.iter()).map(|c| c.len()).sum() });
+
+    let pad = params.pad_border as f32;
+    outlines = map_outlines(outlines, |outline| outline.map(|contour| contour.translate(-pad)));
+
+    // The steps of `Contour::process`. Smoothing one iteration at a time gives the same result as smoothing all at
+    // once, and lets us report each one.
+    for iteration in 1..=params.smooth_iterations {
+        outlines = map_outlines(outlines, |outline| outline.map(|contour| contour.smooth_with(params.smooth_mode, 1)));
+        progress(Progress::Smoothing { iteration, total: params.smooth_iterations });
     }
+
+    let mut outlines = map_outlines(outlines, |outline| {
+        let mut outline = outline.map(|contour| {
+            let contour = contour
+                .scale(f_width, f_height)
+                .simplify_with(params.simplify_mode);
+            match params.resample_spacing {
+                Some(spacing) => contour.resample(spacing),
+                None => contour,
+            }
+        });
+
+        // Simplification can collapse tiny holes.
+        outline.holes.retain(|hole| hole.len() >= 3 && hole.area() >= params.min_area);
+        outline
+    });
+
+    // A contour of fewer than 3 points can't be meshed, even with a `min_area` of 0.
+    outlines.retain(|outline| outline.outer.len() >= 3 && outline.outer.area() >= params.min_area);
+    if outlines.is_empty() {
+        return Err(MeshError::NoSignificantContour);
+    }
+    Ok(outlines)
 }
 
-pub fn find_contour_from_transparency_with_offset(img: &DynamicImage, params: Params) -> Result<Contour, &'static str> {
+/// Applies `f` to every outline, in parallel with the `parallel` feature, as the outlines don't depend on each other.
+fn map_outlines(outlines: Vec<Outline>, f: impl Fn(Outline) -> Outline + Send + Sync) -> Vec<Outline> {
+    #[cfg(feature = "parallel")]
+    let outlines = outlines.into_par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let outlines = outlines.into_iter();
+
+    outlines.map(f).collect()
+}
 
+/// Extracts the coverage of the shape from the image, 255 being fully part of the shape.
+pub(crate) fn mask_from_source(img: &DynamicImage, mask_source: MaskSource) -> GrayImage {
     let (width, height) = img.dimensions();
 
     let mut imgbuf = image::GrayImage::new(width, height);    
 
+    // `get_pixel` scales 16 bit and floating point channels to 8 bits, the precision of the distance field.
     for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
-        *pixel = Luma([img.get_pixel(x, y).channels()[3]]);
+        let source = img.get_pixel(x, y);
+        *pixel = match mask_source {
+            MaskSource::Alpha => Luma([source.channels()[3]]),
+            MaskSource::Luminance => source.to_luma(),
+            MaskSource::ColorKey { rgb, tolerance } => {
+                let is_key = source.channels()[..3].iter().zip(rgb).all(|(c, k)| c.abs_diff(k) <= tolerance);
+                Luma([if is_key { 0 } else { 255 }])
+            }
+        };
     }
 
-    let sdf = sdf_image(width, height, params.border_offset, &imgbuf);
+    imgbuf
+}
 
-    let (f_width, f_height) = (width as f32, height as f32);
+/// Surrounds the image with `pad` pixels of background on every side.
+fn pad_image(image: &GrayImage, pad: u32) -> GrayImage {
+    if pad == 0 {
+        return image.clone();
+    }
 
-    Ok(
-        find_contour_from_grayscale(&sdf, 128u8)?
-        .smooth(params.smooth_iterations)
-        .scale(f_width, f_height)
-        .simplify(params.simplify_angle))
+    let mut padded = GrayImage::new(image.width() + 2 * pad, image.height() + 2 * pad);
+    image::imageops::replace(&mut padded, image, pad as i64, pad as i64);
+    padded
 }
 
-pub fn find_contour_from_grayscale(image: &GrayImage, threshold: u8) -> Result<Contour, &'static str> {
-    // Find a starting point
-    let mut start_point:Option<[u32; 2]> = None;
+/// Replaces soft edges with the crisp, anti-aliased edge the distance field expects, at the level estimated by
+/// `Params::adaptive_threshold`. The distance field takes every partly covered pixel as part of an edge, so a wide
+/// glow would otherwise grow the shape to where the glow fades out completely. Every pixel is covered by how far
+/// it lies inside the level, estimated from the coverage and its gradient.
+fn adapt_coverage(coverage: &GrayImage) -> GrayImage {
+    let (width, height) = coverage.dimensions();
+    let value = |x: i64, y: i64| coverage.get_pixel(x.clamp(0, width as i64 - 1) as u32, y.clamp(0, height as i64 - 1) as u32)[0] as f64;
 
-    for (x, y, pixel) in image.enumerate_pixels() {
-        // As we're looking below, skip the last row
-        if y == image.height() - 1 { continue };
-        
+    // The coverage weighted by the squared gradient, and the sum of the weights, per tile.
+    let (n_tiles_x, n_tiles_y) = (width.div_ceil(ADAPTIVE_TILE_SIZE), height.div_ceil(ADAPTIVE_TILE_SIZE));
+    let mut sums = vec![[0.0f64; 2]; (n_tiles_x * n_tiles_y) as usize];
+    let mut gradients = vec![0.0f32; (width * height) as usize];
+    for (x, y, pixel) in coverage.enumerate_pixels() {
+        let (xi, yi) = (x as i64, y as i64);
+        let gradient = [(value(xi + 1, yi) - value(xi - 1, yi)) / 2.0, (value(xi, yi + 1) - value(xi, yi - 1)) / 2.0];
+        let weight = gradient[0] * gradient[0] + gradient[1] * gradient[1];
+        gradients[(y * width + x) as usize] = weight.sqrt() as f32;
+        let tile = &mut sums[((y / ADAPTIVE_TILE_SIZE) * n_tiles_x + x / ADAPTIVE_TILE_SIZE) as usize];
+        tile[0] += weight * pixel[0] as f64;
+        tile[1] += weight;
+    }
+
+    // Without any edge, there is nothing to adapt to.
+    let [total, total_weight] = sums.iter().fold([0.0, 0.0], |[s, w], [ts, tw]| [s + ts, w + tw]);
+    if total_weight == 0.0 {
+        return coverage.clone();
+    }
+
+    // Every tile is pooled with the tiles up to two away, so an edge softer than a tile is wide is seen as a whole
+    // rather than as the part of it that falls inside the tile. Tiles without an edge nearby use the level of the
+    // whole image.
+    let pooled = |tx: u32, ty: u32| {
+        let (xs, ys) = (tx.saturating_sub(2)..(tx + 3).min(n_tiles_x), ty.saturating_sub(2)..(ty + 3).min(n_tiles_y));
+        ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
+            .fold([0.0, 0.0], |[s, w], (x, y)| {
+                let [ts, tw] = sums[(y * n_tiles_x + x) as usize];
+                [s + ts, w + tw]
+            })
+    };
+    let levels: Vec<f32> = (0..n_tiles_x * n_tiles_y)
+        .map(|i| match pooled(i % n_tiles_x, i / n_tiles_x) {
+            [s, w] if w > 0.0 => s / w,
+            _ => total / total_weight,
+        } as f32)
+        .collect();
+    let level_at = |x: u32, y: u32| {
+        // Bilinear between the centers of the four nearest tiles.
+        let position = |p: u32, n_tiles: u32| {
+            let t = ((p as f32 + 0.5) / ADAPTIVE_TILE_SIZE as f32 - 0.5).clamp(0.0, (n_tiles - 1) as f32);
+            let i = (t.floor() as u32).min(n_tiles.saturating_sub(2));
+            (i, (i + 1).min(n_tiles - 1), t - i as f32)
+        };
+        let ((x0, x1, fx), (y0, y1, fy)) = (position(x, n_tiles_x), position(y, n_tiles_y));
+        let level = |tx: u32, ty: u32| levels[(ty * n_tiles_x + tx) as usize];
+        let top = level(x0, y0) * (1.0 - fx) + level(x1, y0) * fx;
+        let bottom = level(x0, y1) * (1.0 - fx) + level(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    };
+
+    GrayImage::from_fn(width, height, |x, y| {
+        let (v, level) = (coverage.get_pixel(x, y)[0] as f32, level_at(x, y));
+        let gradient = gradients[(y * width + x) as usize];
+        // The distance to the level in pixels, positive inside, and the pixel is covered up to that distance past its center.
+        let inside = match gradient > 0.0 {
+            true => (v - level) / gradient,
+            false => match v.total_cmp(&level) {
+                std::cmp::Ordering::Greater => 1.0,
+                std::cmp::Ordering::Less => -1.0,
+                std::cmp::Ordering::Equal => 0.0,
+            },
+        };
+        Luma([(127.5 + 255.0 * inside).round().clamp(0.0, 255.0) as u8])
+    })
+}
 
-        if pixel[0] <= threshold && image.get_pixel(x, y + 1)[0] > threshold {
-            start_point = Some([x, y]);
-            break;
+pub(crate) fn binarize(mask: &GrayImage, threshold: u8) -> GrayImage {
+    GrayImage::from_fn(mask.width(), mask.height(), |x, y| Luma([if mask.get_pixel(x, y)[0] > threshold { 255 } else { 0 }]))
+}
+
+/// Traces the boundary of the first shape in the image, between pixels above `threshold` and the others.
+///
+/// The points are in pixels of `image`, see `Contour`. `Contour::process` takes them to the [0, 1] image space.
+pub fn find_contour_from_grayscale<F: ThresholdField + ?Sized>(image: &F, threshold: u8) -> Result<Contour, MeshError> {
+    contour_from_grayscale(image, threshold, &TraceOptions::default())
+}
+
+/// Same as `find_contour_from_grayscale`, but for a grid of `width` by `height` cells where `is_inside` tells
+/// which cells are part of the shape, e.g. an occupancy grid from another segmentation.
+///
+/// `is_inside` is only called for cells inside the grid, and cells outside count as background. Without values to
+/// interpolate between, the contour runs halfway between the cells inside and outside the shape.
+pub fn find_contour_from_fn(width: u32, height: u32, is_inside: impl Fn(i32, i32) -> bool) -> Result<Contour, MeshError> {
+    find_contour_from_grayscale(&InsideFn { width, height, is_inside }, 127)
+}
+
+/// `find_contour_from_grayscale`, with the tracer limited by `options`.
+fn contour_from_grayscale<F: ThresholdField + ?Sized>(image: &F, threshold: u8, options: &TraceOptions) -> Result<Contour, MeshError> {
+    let start_point = find_start_point(image, threshold)?;
+
+    trace_contour(image, threshold, options, start_point, |_, _| {})
+}
+
+/// Finds the outer contour of the first shape in the image, together with the contours of the holes inside it.
+///
+/// Holes are traced with the same algorithm as the outer contour, so they wind in the opposite direction.
+/// Shapes lying inside a hole are not part of the outline, and neither are their holes.
+pub fn find_outline_from_grayscale<F: ThresholdField + ?Sized>(image: &F, threshold: u8) -> Result<Outline, MeshError> {
+    outline_from_grayscale(image, threshold, &TraceOptions::default())
+}
+
+fn outline_from_grayscale<F: ThresholdField + ?Sized>(image: &F, threshold: u8, options: &TraceOptions) -> Result<Outline, MeshError> {
+    let start_point = find_start_point(image, threshold)?;
+
+    // Pixels the tracer has passed while looking right, i.e. pixels that were used as a starting point candidate.
+    let mut visited = vec![false; candidate_count(image)];
+    let mark_visited = |visited: &mut Vec<bool>, point: [i64; 2], direction: LookDirection| {
+        if direction == LookDirection::Right {
+            if let Some(index) = candidate_index(image, point) {
+                visited[index] = true;
+            }
+        }
+    };
+
+    let outer = trace_contour(image, threshold, options, start_point, |p, d| mark_visited(&mut visited, p, d))?;
+    let outer_area = signed_area(&outer);
+
+    let mut holes: Vec<Contour> = vec![];
+    let mut islands: Vec<Contour> = vec![];
+
+    for [x, y] in start_candidates(image, threshold) {
+        if visited[candidate_index(image, [x, y]).unwrap()] { continue };
+
+        let point = [x as f32, y as f32];
+        if !contains_point(&outer, point) || islands.iter().any(|island| contains_point(island, point)) {
+            continue;
+        }
+
+        let contour = trace_contour(image, threshold, options, [x, y], |p, d| mark_visited(&mut visited, p, d))?;
+
+        // Single pixel pinholes, e.g. where two parts of the shape almost touch.
+        if contour.len() < 3 { continue };
+
+        // A loop winding the same way as the outer contour is the outside of a separate shape
+        // lying in one of the holes. Everything inside it is skipped.
+        if (signed_area(&contour) > 0.0) == (outer_area > 0.0) {
+            islands.push(contour);
+        } else {
+            holes.push(contour);
         }
     }
 
-    if start_point.is_none() {
-        return Err("No starting point found in the grayscale image.");
+    Ok(Outline { outer, holes })
+}
+
+/// Finds the outer contour of every shape in the image, including shapes lying in the holes of other shapes.
+///
+/// Every pixel the tracer passes is marked as visited, so a loop is never traced again from a later start point.
+pub fn find_all_contours_from_grayscale<F: ThresholdField + ?Sized>(image: &F, threshold: u8) -> Result<Vec<Contour>, MeshError> {
+    Ok(find_all_outlines_from_grayscale(image, threshold)?.into_iter().map(|outline| outline.outer).collect())
+}
+
+/// Same as `find_all_contours_from_grayscale`, but also finds the holes inside each shape.
+pub fn find_all_outlines_from_grayscale<F: ThresholdField + ?Sized>(image: &F, threshold: u8) -> Result<Vec<Outline>, MeshError> {
+    all_outlines_from_grayscale(image, threshold, &TraceOptions::default())
+}
+
+fn all_outlines_from_grayscale<F: ThresholdField + ?Sized>(image: &F, threshold: u8, options: &TraceOptions) -> Result<Vec<Outline>, MeshError> {
+    let start_point = find_start_point(image, threshold)?;
+
+    let mut visited = vec![false; candidate_count(image)];
+    let mark_visited = |visited: &mut Vec<bool>, point: [i64; 2], direction: LookDirection| {
+        if direction == LookDirection::Right {
+            if let Some(index) = candidate_index(image, point) {
+                visited[index] = true;
+            }
+        }
+    };
+
+    let mut loops = vec![trace_contour(image, threshold, options, start_point, |p, d| mark_visited(&mut visited, p, d))?];
+
+    for [x, y] in start_candidates(image, threshold) {
+        if visited[candidate_index(image, [x, y]).unwrap()] { continue };
+
+        let contour = trace_contour(image, threshold, options, [x, y], |p, d| mark_visited(&mut visited, p, d))?;
+
+        // Single pixel pinholes, e.g. where two parts of the shape almost touch.
+        if contour.len() < 3 { continue };
+
+        loops.push(contour);
     }
 
-    let start_point = start_point.unwrap();
+    Ok(outlines_from_loops(loops))
+}
+
+/// Groups loops into outlines. Loops winding like an outer contour start a new outline, and every other loop
+/// becomes a hole of the smallest outer contour containing it.
+fn outlines_from_loops(loops: Vec<Contour>) -> Vec<Outline> {
+    let (boundaries, holes): (Vec<Contour>, Vec<Contour>) = loops.into_iter().partition(|c| signed_area(c) > 0.0);
+
+    let mut outlines: Vec<Outline> = boundaries.into_iter().map(|outer| Outline { outer, holes: vec![] }).collect();
+
+    for hole in holes {
+        let owner = outlines.iter_mut()
+            .filter(|outline| contains_point(&outline.outer, hole[0]))
+            .min_by(|a, b| signed_area(&a.outer).total_cmp(&signed_area(&b.outer)));
+        if let Some(owner) = owner {
+            owner.holes.push(hole);
+        }
+    }
+
+    outlines
+}
+
+/// Finds every boundary between pixels above and pixels at or below `threshold` with marching squares.
+///
+/// Unlike `find_contour_from_grayscale`, the points are interpolated on both axes and every loop is returned,
+/// both the outer boundaries of all shapes and their holes. Outer boundaries wind like the contours of
+/// `find_contour_from_grayscale`, and holes wind in the opposite direction.
+pub fn find_contours_marching_squares<F: ThresholdField + ?Sized>(image: &F, threshold: u8) -> Result<Vec<Contour>, MeshError> {
+    if image.width() < 2 || image.height() < 2 {
+        return Err(MeshError::ImageTooSmall);
+    }
+
+    let contours: Vec<Contour> = marching_squares::trace_isolines(image, threshold)
+        .into_iter()
+        .filter(|contour| contour.len() >= 3)
+        // Marching squares keeps the shape on the other side of the loop than square tracing does.
+        .map(|contour| contour.into_iter().rev().collect())
+        .collect();
+
+    if contours.is_empty() {
+        return Err(MeshError::NoContourFound);
+    }
+
+    Ok(contours)
+}
+
+/// Same as `find_outline_from_grayscale`, but with the loops found by `find_contours_marching_squares`.
+///
+/// The outer contour is the outer boundary reaching highest up in the image.
+fn find_outline_marching_squares<F: ThresholdField + ?Sized>(image: &F, threshold: u8) -> Result<Outline, MeshError> {
+    let mut outlines = outlines_from_loops(find_contours_marching_squares(image, threshold)?);
+
+    let topmost = |outline: &Outline| outline.outer.iter().map(|p| (p[1], p[0])).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap();
+    let first = (0..outlines.len())
+        .min_by(|a, b| topmost(&outlines[*a]).partial_cmp(&topmost(&outlines[*b])).unwrap())
+        .ok_or(MeshError::NoContourFound)?;
+
+    Ok(outlines.swap_remove(first))
+}
+
+fn find_start_point<F: ThresholdField + ?Sized>(image: &F, threshold: u8) -> Result<[i64; 2], MeshError> {
+    if image.width() < 2 || image.height() < 2 {
+        return Err(MeshError::ImageTooSmall);
+    }
+
+    start_candidates(image, threshold).next().ok_or(MeshError::NoContourFound)
+}
+
+/// The value of a pixel, where pixels outside the image are background.
+fn pixel_value<F: ThresholdField + ?Sized>(image: &F, x: i64, y: i64) -> u8 {
+    if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+        return 0;
+    }
+    image.value_at(x as u32, y as u32)
+}
+
+/// Iterates, row by row, over the background pixels directly above a pixel of the shape.
+/// This includes the row above the image, so shapes touching the top of the image are found too.
+fn start_candidates<F: ThresholdField + ?Sized>(image: &F, threshold: u8) -> impl Iterator<Item = [i64; 2]> + '_ {
+    let (width, height) = (image.width() as i64, image.height() as i64);
+
+    (-1..height - 1)
+        .flat_map(move |y| (0..width).map(move |x| [x, y]))
+        .filter(move |[x, y]| pixel_value(image, *x, *y) <= threshold && pixel_value(image, *x, *y + 1) > threshold)
+}
+
+/// The number of positions `start_candidates` can return.
+fn candidate_count<F: ThresholdField + ?Sized>(image: &F) -> usize {
+    image.width() as usize * (image.height() as usize + 1)
+}
+
+/// The index of a position among the positions `start_candidates` can return, if it is one of them.
+fn candidate_index<F: ThresholdField + ?Sized>(image: &F, [x, y]: [i64; 2]) -> Option<usize> {
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    if x < 0 || x >= width || y < -1 || y >= height {
+        return None;
+    }
+    Some(((y + 1) * width + x) as usize)
+}
+
+/// How far the square tracing algorithm follows a contour, see `Params::max_trace_steps`,
+/// `Params::close_unclosed_contours` and `Params::close_tolerance`, and where it reports contours it closed itself.
+struct TraceOptions<'a> {
+    max_steps: Option<u32>,
+    close_unclosed: bool,
+    close_tolerance: f32,
+    progress: &'a dyn Fn(Progress),
+}
+
+impl<'a> TraceOptions<'a> {
+    fn from_params(params: &Params, progress: &'a dyn Fn(Progress)) -> Self {
+        TraceOptions {
+            max_steps: params.max_trace_steps,
+            close_unclosed: params.close_unclosed_contours,
+            close_tolerance: params.close_tolerance,
+            progress,
+        }
+    }
+}
+
+impl Default for TraceOptions<'_> {
+    fn default() -> Self {
+        TraceOptions { max_steps: None, close_unclosed: false, close_tolerance: 0.0, progress: &|_| {} }
+    }
+}
+
+fn trace_contour<F: ThresholdField + ?Sized>(
+    image: &F,
+    threshold: u8,
+    options: &TraceOptions,
+    start_point: [i64; 2],
+    mut on_step: impl FnMut([i64; 2], LookDirection))
+    -> Result<Contour, MeshError> {
+
     let mut contour:Contour = Contour::new();
 
     let mut current_direction = LookDirection::Right;
     let mut current_point = start_point;
 
-    let max_iterations = image.width() * image.height();
+    // The tracer can walk along the background just outside the image.
+    let max_iterations = options.max_steps.unwrap_or((image.width() + 2) * (image.height() + 2));
     let mut sanity_check = 0;
 
+    // The pixel and direction every point was found at, to find where the tracer started going round a loop
+    // if it never gets back to the starting point.
+    let mut steps: Vec<([i64; 2], LookDirection)> = vec![];
+
+    // Whether the tracer has been further than `close_tolerance` from the starting point yet, and whether it has
+    // come back within it since.
+    let mut moved_away = false;
+    let mut closing = false;
+
     loop{
 
         sanity_check += 1;
 
         if sanity_check > max_iterations {
-            return Err("It was not possible to find a contour in the image.");
+            if closing { break; }
+            if options.close_unclosed && contour.len() >= 3 {
+                (options.progress)(Progress::ContourNotClosed { points: contour.len() });
+                return Ok(close_traced(contour, &steps, (current_point, current_direction)));
+            }
+            return Err(MeshError::ContourTooComplex { points: contour.len() });
+        }
+
+        // When we come back to the starting point looking the way we started, we're done. The tracer can pass
+        // the starting point looking other ways first, and their crossings belong to the end of the contour.
+        if !contour.is_empty() && current_point == start_point && current_direction == LookDirection::Right { break; }
+
+        if options.close_tolerance > 0.0 {
+            let (dx, dy) = ((current_point[0] - start_point[0]) as f32, (current_point[1] - start_point[1]) as f32);
+            let near_start = (dx * dx + dy * dy).sqrt() <= options.close_tolerance;
+
+            // Back near the starting point, the contour is done. The tracer goes on without adding points, so the
+            // pixels it passes are still marked, until it gets to the starting point or leaves its neighbourhood.
+            if closing && !near_start { break; }
+            closing |= moved_away && near_start;
+            moved_away |= !near_start;
         }
 
-        // When we come back to the starting point, we're done
-        if contour.len() > 0 && current_point==start_point { break;}
+        on_step(current_point, current_direction);
 
         let (x, y) = (current_point[0], current_point[1]);
 
@@ -191,8 +1416,8 @@ pub fn find_contour_from_grayscale(image: &GrayImage, threshold: u8) -> Result<C
             LookDirection::Left => [x, y-1],
             LookDirection::Up => [x+1, y]
         };
-        let img_val0 = image.get_pixel(x,y)[0];
-        let img_val1 = image.get_pixel(comparison_point[0], comparison_point[1])[0];
+        let img_val0 = pixel_value(image, x, y);
+        let img_val1 = pixel_value(image, comparison_point[0], comparison_point[1]);
 
         let outside_val = img_val0 as f32 - threshold as f32;
         let inside_val = img_val1 as f32 - threshold as f32;
@@ -201,16 +1426,21 @@ pub fn find_contour_from_grayscale(image: &GrayImage, threshold: u8) -> Result<C
         
         let new_point = [x as f32*ratio + comparison_point[0] as f32 * (1.0-ratio), y as f32*ratio + comparison_point[1] as f32 * (1.0-ratio)];
 
-        contour.push(new_point);
+        if !closing {
+            contour.push(new_point);
+            if options.close_unclosed {
+                steps.push((current_point, current_direction));
+            }
+        }
     
         match current_direction {
             LookDirection::Right => {
-                if image.get_pixel(x+1, y + 1)[0] <= threshold {
+                if pixel_value(image, x + 1, y + 1) <= threshold {
                     current_direction = LookDirection::Down;
                     current_point = [x+1, y+1];
                     continue;
                 }
-                if image.get_pixel(x + 1, y)[0] <= threshold {
+                if pixel_value(image, x + 1, y) <= threshold {
                     current_point = [x + 1, y];
                     continue;
                 }
@@ -219,12 +1449,12 @@ pub fn find_contour_from_grayscale(image: &GrayImage, threshold: u8) -> Result<C
                 continue;
             },
             LookDirection::Down => {
-                if image.get_pixel(x-1, y + 1)[0] <= threshold {
+                if pixel_value(image, x - 1, y + 1) <= threshold {
                     current_direction = LookDirection::Left;
                     current_point = [x-1, y+1];
                     continue;
                 }
-                if image.get_pixel(x, y+1)[0] <= threshold {
+                if pixel_value(image, x, y + 1) <= threshold {
                     current_point = [x, y+1];
                     continue;
                 }
@@ -233,12 +1463,12 @@ pub fn find_contour_from_grayscale(image: &GrayImage, threshold: u8) -> Result<C
                 continue;
             },
             LookDirection::Left => {
-                if image.get_pixel(x-1, y - 1)[0] <= threshold {
+                if pixel_value(image, x - 1, y - 1) <= threshold {
                     current_direction = LookDirection::Up;
                     current_point = [x-1, y-1];
                     continue;
                 }
-                if image.get_pixel(x - 1, y)[0] <= threshold {
+                if pixel_value(image, x - 1, y) <= threshold {
                     current_point = [x - 1, y];
                     continue;
                 }
@@ -247,12 +1477,12 @@ pub fn find_contour_from_grayscale(image: &GrayImage, threshold: u8) -> Result<C
                 continue;
             },
             LookDirection::Up => {
-                if image.get_pixel(x+1, y - 1)[0] <= threshold {
+                if pixel_value(image, x + 1, y - 1) <= threshold {
                     current_direction = LookDirection::Right;
                     current_point = [x+1, y-1];
                     continue;
                 }
-                if image.get_pixel(x, y - 1)[0] <= threshold {
+                if pixel_value(image, x, y - 1) <= threshold {
                     current_point = [x, y - 1];
                     continue;
                 }
@@ -266,11 +1496,116 @@ pub fn find_contour_from_grayscale(image: &GrayImage, threshold: u8) -> Result<C
     Ok(contour)
 }
 
+/// Closes the points of a contour the tracer didn't get back to the start of. `next` is the pixel and direction the
+/// tracer would have looked at next. If it looked there before, the tracer is going round a loop, and the points
+/// since it last looked there are one round of it. Otherwise all points are kept.
+fn close_traced(contour: Contour, steps: &[([i64; 2], LookDirection)], next: ([i64; 2], LookDirection)) -> Contour {
+    match steps.iter().rposition(|step| *step == next) {
+        Some(loop_start) if contour.len() - loop_start >= 3 => contour.0[loop_start..].iter().copied().collect(),
+        _ => contour,
+    }
+}
+
+
+pub(crate) fn signed_area(contour: &Contour) -> f32 {
+    let n_points = contour.len();
+    let mut area = 0.0;
+
+    for i in 0..n_points {
+        let p0 = contour[i];
+        let p1 = contour[(i + 1) % n_points];
+        area += p0[0] * p1[1] - p1[0] * p0[1];
+    }
+
+    area / 2.0
+}
+
+pub(crate) fn contains_point(contour: &Contour, point: [f32; 2]) -> bool {
+    let n_points = contour.len();
+    let mut inside = false;
+
+    for i in 0..n_points {
+        let p0 = contour[i];
+        let p1 = contour[(i + 1) % n_points];
+
+        if (p0[1] > point[1]) != (p1[1] > point[1]) {
+            let x = p0[0] + (point[1] - p0[1]) / (p1[1] - p0[1]) * (p1[0] - p0[0]);
+            if point[0] < x {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
 
 fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
     [a[0] - b[0], a[1] - b[1]]
 }
 
+fn length(v: [f32; 2]) -> f32 {
+    (v[0] * v[0] + v[1] * v[1]).sqrt()
+}
+
+fn cross(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// Whether the edges `a`-`b` and `c`-`d` cross or touch.
+fn segments_intersect(a: [f32; 2], b: [f32; 2], c: [f32; 2], d: [f32; 2]) -> bool {
+    let [d1, d2, d3, d4] = [cross(c, d, a), cross(c, d, b), cross(a, b, c), cross(a, b, d)];
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return true;
+    }
+
+    // An end point on the other edge.
+    let within = |p: [f32; 2], q: [f32; 2], r: [f32; 2]| {
+        r[0] >= p[0].min(q[0]) && r[0] <= p[0].max(q[0]) && r[1] >= p[1].min(q[1]) && r[1] <= p[1].max(q[1])
+    };
+    (d1 == 0.0 && within(c, d, a)) || (d2 == 0.0 && within(c, d, b)) || (d3 == 0.0 && within(a, b, c)) || (d4 == 0.0 && within(a, b, d))
+}
+
+/// Whether the edge `b`-`c` goes back along the edge `a`-`b` before it.
+fn folds_back(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> bool {
+    let (ab, bc) = (sub(b, a), sub(c, b));
+    cross(a, b, c) == 0.0 && ab[0] * bc[0] + ab[1] * bc[1] < 0.0
+}
+
+pub(crate) fn distance_to_segment(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let ab = sub(b, a);
+    let ap = sub(p, a);
+    let len_sq = ab[0] * ab[0] + ab[1] * ab[1];
+    if len_sq == 0.0 {
+        return length(ap);
+    }
+
+    let t = ((ap[0] * ab[0] + ap[1] * ab[1]) / len_sq).clamp(0.0, 1.0);
+    length(sub(ap, [ab[0] * t, ab[1] * t]))
+}
+
+/// Marks the points of the open polyline `indices` that Ramer–Douglas–Peucker keeps.
+pub(crate) fn rdp_mark(points: &[[f32; 2]], indices: &[usize], epsilon: f32, keep: &mut [bool]) {
+    let mut stack = vec![(0, indices.len() - 1)];
+
+    while let Some((start, end)) = stack.pop() {
+        if end <= start + 1 {
+            continue;
+        }
+
+        let (a, b) = (points[indices[start]], points[indices[end]]);
+        let (furthest, distance) = (start + 1..end)
+            .map(|i| (i, distance_to_segment(points[indices[i]], a, b)))
+            .max_by(|x, y| x.1.total_cmp(&y.1))
+            .unwrap();
+
+        if distance > epsilon {
+            keep[indices[furthest]] = true;
+            stack.push((start, furthest));
+            stack.push((furthest, end));
+        }
+    }
+}
+
 fn normalize(v: [f32; 2]) -> [f32; 2] {
     let norm = (v[0] * v[0] + v[1] * v[1]).sqrt();
     [v[0] / norm, v[1] / norm]
@@ -280,8 +1615,9 @@ fn sdf_image(
     width: u32, 
     height: u32,
     offset: f32,
+    cutoff: f32,
     imgbuf: &ImageBuffer<Luma<u8>, Vec<u8>>) 
-    -> ImageBuffer<Luma<u8>, Vec<u8>> {
+    -> DistanceField {
     
     let mut bitmap: Image2d<Unorm8, Vec<Unorm8>> = sdfer::Image2d::from_fn(width as usize, height as usize, |x, y| {
         let pixel = imgbuf.get_pixel(x as u32, y as u32).channels()[0];
@@ -290,17 +1626,184 @@ fn sdf_image(
 
     let sdf = sdfer::esdt::glyph_to_sdf(&mut bitmap, sdfer::esdt::Params{
         radius: offset,
-        cutoff: 0.0,
+        cutoff,
         ..Default::default()
     }, None).0;
 
-    let mut imgbuf2 = image::GrayImage::new(width, height);
+    DistanceField { sdf, width, height }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplify_keeps_only_the_corners_of_a_square() {
+        let square = Contour::from_points(vec![
+            [0.0, 0.0], [0.25, 0.0], [0.5, 0.0], [1.0, 0.0],
+            [1.0, 0.5], [1.0, 1.0],
+            [0.75, 1.0], [0.0, 1.0],
+            [0.0, 0.75], [0.0, 0.25],
+        ]).unwrap();
+
+        let simplified = square.simplify(PI / 30.0);
 
-    for (x, y, pixel) in imgbuf2.enumerate_pixels_mut() {
-        let (xs, ys) = (x as usize, y as usize);
-        let sdf_value = sdf[(xs, ys)].to_bits();
-        *pixel = Luma([sdf_value]);
+        assert_eq!(simplified.into_points(), vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
     }
-    
-    imgbuf2
-}
\ No newline at end of file
+
+    #[test]
+    fn trace_threshold_without_border_offset_is_threshold() {
+        let params = Params::builder().threshold(100).mask_offset(2.0).border_offset(0.0).build();
+        assert_eq!(params.trace_threshold(), 100);
+
+        let params = Params::builder().threshold(100).mask_offset(2.0).border_offset(-5.0).build();
+        assert_eq!(params.trace_threshold(), 100);
+    }
+
+    #[test]
+    fn simplify_leaves_degenerate_contours_alone() {
+        assert!(Contour::new().simplify(PI / 30.0).is_empty());
+        let line: Contour = [[0.0, 0.0], [1.0, 0.0]].into_iter().collect();
+        assert_eq!(line.simplify(PI / 30.0).len(), 2);
+    }
+
+    /// A regular polygon of `n` points around (0.5, 0.5), with every point moved in or out by `noise`.
+    fn noisy_circle(n: usize, noise: f32) -> Contour {
+        (0..n).map(|i| {
+            let angle = 2.0 * PI * i as f32 / n as f32;
+            let r = 0.4 + if i % 2 == 0 { noise } else { -noise };
+            [0.5 + r * angle.cos(), 0.5 + r * angle.sin()]
+        }).collect()
+    }
+
+    #[test]
+    fn smooth_averages_each_point_with_its_neighbours() {
+        let contour = noisy_circle(12, 0.05);
+        let points = contour.as_slice().to_vec();
+        let n = points.len();
+        let mut expected = points.clone();
+        for _ in 0..2 {
+            expected = (0..n).map(|i| {
+                let (prev, current, next) = (expected[(i + n - 1) % n], expected[i], expected[(i + 1) % n]);
+                [(prev[0] + current[0] + next[0]) / 3.0, (prev[1] + current[1] + next[1]) / 3.0]
+            }).collect();
+        }
+
+        let smoothed = contour.smooth(2);
+
+        assert_eq!(smoothed.len(), n);
+        for (p, q) in smoothed.iter().zip(&expected) {
+            assert!((p[0] - q[0]).abs() < 1e-6 && (p[1] - q[1]).abs() < 1e-6, "{:?} != {:?}", p, q);
+        }
+    }
+
+    #[test]
+    fn taubin_keeps_the_area_that_averaging_shrinks() {
+        let contour = noisy_circle(32, 0.01);
+        let area = contour.area();
+
+        let averaged = contour.clone().smooth(10).area();
+        let taubin = contour.smooth_taubin(10, 0.5, -0.53).area();
+
+        assert!(averaged < 0.9 * area, "{} of {}", averaged, area);
+        assert!((taubin - area).abs() < 0.02 * area, "{} of {}", taubin, area);
+    }
+
+    /// A 128 by 128 image of a disc whose alpha falls from opaque at `inner` pixels from the center to transparent
+    /// at `outer` pixels.
+    fn soft_disc(inner: f32, outer: f32) -> DynamicImage {
+        image::RgbaImage::from_fn(128, 128, |x, y| {
+            let r = ((x as f32 + 0.5 - 64.0).powi(2) + (y as f32 + 0.5 - 64.0).powi(2)).sqrt();
+            let alpha = ((outer - r) / (outer - inner)).clamp(0.0, 1.0);
+            image::Rgba([255, 255, 255, (alpha * 255.0).round() as u8])
+        }).into()
+    }
+
+    #[test]
+    fn higher_threshold_shrinks_the_contour() {
+        let image = soft_disc(20.0, 40.0);
+        let area = |threshold| {
+            let params = Params::builder().threshold(threshold).build();
+            find_contour_from_transparency_with_offset(&image, params).unwrap().area()
+        };
+
+        let (low, middle, high) = (area(64), area(128), area(192));
+        assert!(low > middle && middle > high, "{} {} {}", low, middle, high);
+    }
+
+    /// The smallest and largest x and y of the contour, which must have at least 3 points, all finite.
+    fn bounds(contour: &Contour) -> [[f32; 2]; 2] {
+        assert!(contour.len() >= 3 && contour.iter().flatten().all(|c| c.is_finite()), "{:?}", contour);
+        contour.iter().fold([[f32::MAX; 2], [f32::MIN; 2]], |[min, max], p| {
+            [[min[0].min(p[0]), min[1].min(p[1])], [max[0].max(p[0]), max[1].max(p[1])]]
+        })
+    }
+
+    #[test]
+    fn single_pixel_in_a_tiny_image_gives_a_contour() {
+        // Only images less than 2 pixels wide or high are too small, so this one has to be traced.
+        let mask = GrayImage::from_fn(3, 3, |x, y| Luma([if (x, y) == (1, 1) { 255 } else { 0 }]));
+        let [min, max] = bounds(&find_contour_from_grayscale(&mask, 127).unwrap());
+        assert!(min[0] < 1.0 && min[1] < 1.0 && max[0] > 1.0 && max[1] > 1.0, "{:?} {:?}", min, max);
+
+        let image = image::RgbaImage::from_fn(3, 3, |x, y| image::Rgba([0, 0, 0, mask.get_pixel(x, y)[0]])).into();
+        let [min, max] = bounds(&find_contour_from_transparency_with_offset(&image, Params::default()).unwrap());
+        assert!(min[0] < 0.5 && min[1] < 0.5 && max[0] > 0.5 && max[1] > 0.5, "{:?} {:?}", min, max);
+    }
+
+    #[test]
+    fn shape_flush_with_the_border_gives_a_contour_to_the_edge() {
+        // The left half of the image, touching the top, left and bottom edges.
+        let mask = GrayImage::from_fn(16, 16, |x, _| Luma([if x < 8 { 255 } else { 0 }]));
+        let [min, max] = bounds(&find_contour_from_grayscale(&mask, 127).unwrap());
+        assert!(min[0] <= 0.0 && min[1] <= 0.0 && max[1] >= 15.0, "{:?} {:?}", min, max);
+        assert!(max[0] > 7.0 && max[0] < 8.0, "{:?}", max);
+
+        // Without padding, the contour runs along the outermost pixels, and with it, it grows past them.
+        let image = image::RgbaImage::from_fn(16, 16, |x, _| image::Rgba([0, 0, 0, if x < 8 { 255 } else { 0 }])).into();
+        let [min, max] = bounds(&find_contour_from_transparency_with_offset(&image, Params::default()).unwrap());
+        assert!(min[0] <= 0.0 && min[1] <= 0.0 && max[1] >= 15.0 / 16.0, "{:?} {:?}", min, max);
+
+        let params = Params::builder().pad_border(20).build();
+        let [min, max] = bounds(&find_contour_from_transparency_with_offset(&image, params).unwrap());
+        assert!(min[0] < 0.0 && min[1] < 0.0 && max[1] > 1.0, "{:?} {:?}", min, max);
+    }
+
+    #[test]
+    fn adaptive_threshold_traces_a_radial_gradient_at_half_alpha() {
+        // The alpha falls from opaque to transparent between 16 and 48 pixels from the center, and is half
+        // covered at 32 pixels, which is where the edge is seen. The contour should be where it is for a crisp
+        // disc with its edge there, as the distance field grows both the same.
+        let radius = |image: &DynamicImage, adaptive_threshold| {
+            let params = Params::builder().adaptive_threshold(adaptive_threshold).build();
+            let area = find_contour_from_transparency_with_offset(image, params).unwrap().area();
+            (area / PI).sqrt() * 128.0
+        };
+        let crisp = radius(&soft_disc(31.5, 32.5), false);
+
+        let gradient = soft_disc(16.0, 48.0);
+        let (adaptive, global) = (radius(&gradient, true), radius(&gradient, false));
+        assert!((adaptive - crisp).abs() < 1.5, "{} {}", adaptive, crisp);
+        assert!((adaptive - crisp).abs() < (global - crisp).abs(), "{} {} {}", adaptive, global, crisp);
+    }
+
+    #[test]
+    fn sixteen_bit_image_gives_the_same_contour_as_eight_bit() {
+        let image = soft_disc(20.0, 40.0);
+        let wide = DynamicImage::ImageRgba16(image.to_rgba16());
+        assert_eq!(wide.color(), image::ColorType::Rgba16);
+
+        let contour = find_contour_from_transparency_with_offset(&image, Params::default()).unwrap();
+        let wide_contour = find_contour_from_transparency_with_offset(&wide, Params::default()).unwrap();
+
+        assert_eq!(wide_contour.into_points(), contour.into_points());
+    }
+
+    #[test]
+    fn running_out_of_steps_is_too_complex() {
+        let image = soft_disc(20.0, 20.5);
+        let params = Params::builder().max_trace_steps(10).build();
+        let result = find_contour_from_transparency_with_offset(&image, params);
+        assert!(matches!(result, Err(MeshError::ContourTooComplex { points }) if points > 0), "{:?}", result);
+    }
+}
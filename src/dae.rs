@@ -0,0 +1,166 @@
+use crate::{Mesh, MeshError};
+use obj_exporter::Primitive;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// A fixed date keeps the output the same from run to run, like the dates in the 3MF zip.
+const TIMESTAMP: &str = "1970-01-01T00:00:00Z";
+
+/// Saves a mesh to a Collada (`.dae`) file, for pipelines that don't read OBJ or glTF.
+///
+/// The positions, normals and uvs are written as separate sources, and every triangle corner lists its
+/// position, normal and uv index in that order, like the corners of an OBJ face. The whole mesh uses one
+/// material, textured with the image next to the file with the same name and a `.png` extension, like the
+/// one `create_and_save_mesh_from_image` writes. Normals and uvs are left out if any triangle corner has none.
+/// Points and lines are not written.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to save.
+/// * `file_path` - The file path to save the mesh to.
+///
+/// # Returns
+///
+/// A `Result` which is `Ok` if the mesh was saved successfully, or an `Err` containing a `MeshError`.
+pub fn save_mesh_to_dae(mesh: &Mesh, file_path: impl AsRef<Path>) -> Result<(), MeshError> {
+    let file_path = file_path.as_ref();
+    let texture_name = file_path
+        .with_extension("png")
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("material.png")
+        .to_string();
+
+    let mut file = BufWriter::new(File::create(file_path)?);
+    file.write_all(collada_xml(mesh, &texture_name).as_bytes())?;
+    file.flush()?;
+    Ok(())
+}
+
+fn collada_xml(mesh: &Mesh, texture_name: &str) -> String {
+    let triangles: Vec<_> = mesh.triangles.iter().filter_map(|triangle| match triangle {
+        Primitive::Triangle(c0, c1, c2) => Some([*c0, *c1, *c2]),
+        _ => None,
+    }).collect();
+    let corners = || triangles.iter().flatten();
+    let has_normals = corners().all(|(_, _, n)| n.is_some_and(|n| n < mesh.normals.len()));
+    let has_uvs = corners().all(|(_, t, _)| t.is_some_and(|t| t < mesh.uv_vertices.len()));
+
+    let mut sources = source("positions", &["X", "Y", "Z"], mesh.vertices.iter().map(|v| [v.x, v.y, v.z]));
+    let mut inputs = String::from(r##"<input semantic="VERTEX" source="#mesh-vertices" offset="0"/>"##);
+    if has_normals {
+        sources.push_str(&source("normals", &["X", "Y", "Z"], mesh.normals.iter().map(|n| [n.x, n.y, n.z])));
+        let _ = write!(inputs, r##"<input semantic="NORMAL" source="#mesh-normals" offset="1"/>"##);
+    }
+    if has_uvs {
+        sources.push_str(&source("uvs", &["S", "T"], mesh.uv_vertices.iter().map(|t| [t.u, t.v])));
+        let _ = write!(inputs, r##"<input semantic="TEXCOORD" source="#mesh-uvs" offset="{}" set="0"/>"##, 1 + has_normals as usize);
+    }
+
+    let mut indices = String::new();
+    for (v, t, n) in corners() {
+        let _ = write!(indices, "{} ", v);
+        if has_normals {
+            let _ = write!(indices, "{} ", n.unwrap_or_default());
+        }
+        if has_uvs {
+            let _ = write!(indices, "{} ", t.unwrap_or_default());
+        }
+    }
+
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="utf-8"?>"#,
+            r#"<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">"#,
+            r#"<asset><contributor><authoring_tool>image_to_mesh</authoring_tool></contributor>"#,
+            r#"<created>{timestamp}</created><modified>{timestamp}</modified><unit name="meter" meter="1"/><up_axis>Y_UP</up_axis></asset>"#,
+            r#"<library_images><image id="texture" name="texture"><init_from>{texture}</init_from></image></library_images>"#,
+            r#"<library_effects><effect id="material-effect"><profile_COMMON>"#,
+            r#"<newparam sid="texture-surface"><surface type="2D"><init_from>texture</init_from></surface></newparam>"#,
+            r#"<newparam sid="texture-sampler"><sampler2D><source>texture-surface</source></sampler2D></newparam>"#,
+            r#"<technique sid="common"><lambert><diffuse><texture texture="texture-sampler" texcoord="uv"/></diffuse></lambert></technique>"#,
+            r#"</profile_COMMON></effect></library_effects>"#,
+            r##"<library_materials><material id="material" name="material"><instance_effect url="#material-effect"/></material></library_materials>"##,
+            r#"<library_geometries><geometry id="mesh" name="mesh"><mesh>{sources}"#,
+            r##"<vertices id="mesh-vertices"><input semantic="POSITION" source="#mesh-positions"/></vertices>"##,
+            r#"<triangles material="material" count="{count}">{inputs}<p>{indices}</p></triangles>"#,
+            r#"</mesh></geometry></library_geometries>"#,
+            r#"<library_visual_scenes><visual_scene id="scene" name="scene"><node id="node" name="mesh">"#,
+            r##"<instance_geometry url="#mesh"><bind_material><technique_common><instance_material symbol="material" target="#material">"##,
+            r#"<bind_vertex_input semantic="uv" input_semantic="TEXCOORD" input_set="0"/>"#,
+            r#"</instance_material></technique_common></bind_material></instance_geometry>"#,
+            r#"</node></visual_scene></library_visual_scenes>"#,
+            r##"<scene><instance_visual_scene url="#scene"/></scene>"##,
+            r#"</COLLADA>"#,
+        ),
+        timestamp = TIMESTAMP,
+        texture = escape_xml(texture_name),
+        sources = sources,
+        count = triangles.len(),
+        inputs = inputs,
+        indices = indices.trim_end(),
+    )
+}
+
+/// Replaces the characters that can't appear as they are in XML text or attribute values with their entities.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A `<source>` named `mesh-{name}`, with one element of `params.len()` floats per item.
+fn source<const N: usize>(name: &str, params: &[&str; N], items: impl Iterator<Item = [f64; N]>) -> String {
+    let mut values = String::new();
+    let mut count = 0;
+    for item in items {
+        for value in item {
+            let _ = write!(values, "{} ", value as f32);
+        }
+        count += 1;
+    }
+
+    let params: String = params.iter().map(|param| format!(r#"<param name="{}" type="float"/>"#, param)).collect();
+    format!(
+        concat!(
+            r#"<source id="mesh-{name}"><float_array id="mesh-{name}-array" count="{values}">{array}</float_array>"#,
+            r##"<technique_common><accessor source="#mesh-{name}-array" count="{count}" stride="{stride}">{params}</accessor></technique_common>"##,
+            r#"</source>"#,
+        ),
+        name = name,
+        values = count * N,
+        array = values.trim_end(),
+        count = count,
+        stride = N,
+        params = params,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Surface;
+    use obj_exporter::Vertex;
+
+    #[test]
+    fn texture_name_is_escaped() {
+        let vertices = vec![Vertex{x: 0.0, y: 0.0, z: 0.0}, Vertex{x: 1.0, y: 0.0, z: 0.0}, Vertex{x: 0.0, y: 1.0, z: 0.0}];
+        let triangles = vec![Primitive::Triangle((0, None, None), (1, None, None), (2, None, None))];
+        let mesh = Mesh{vertices, triangles, uv_vertices: vec![], normals: vec![], surfaces: vec![Surface::Front], colors: vec![], quads: vec![]};
+
+        let xml = collada_xml(&mesh, r#"a&b <"c">.png"#);
+
+        assert!(xml.contains("<init_from>a&amp;b &lt;&quot;c&quot;&gt;.png</init_from>"), "{}", xml);
+    }
+}
@@ -0,0 +1,424 @@
+use crate::{Mesh, Surface, WELD_EPSILON};
+use obj_exporter::Primitive;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+type Corner = (usize, Option<usize>, Option<usize>);
+
+// A collapse is refused if it turns a triangle by more than this (cosine of 60 degrees).
+const MIN_NORMAL_COS: f64 = 0.5;
+
+impl Mesh {
+    /// Removes triangles with quadric error metric edge collapses until at most `target_triangles` are left.
+    ///
+    /// Each collapse merges one vertex into a neighbouring one, picking the cheapest collapse first, where the cost
+    /// is the squared distance of the kept vertex to the planes of the triangles around both vertices.
+    /// Vertices are never moved, so the remaining vertices, uvs and normals are a subset of the original ones.
+    ///
+    /// Coincident vertices are treated as one, so the caps and side walls of an unwelded mesh are decimated together
+    /// without opening cracks between them. Collapses are refused if they would:
+    /// * touch an open boundary of the mesh, such as the silhouette of a `MeshStyle::FrontPlaneOnly` mesh,
+    /// * make the mesh non-manifold or flip a triangle,
+    /// * move a corner across a uv or normal seam at the removed vertex.
+    ///
+    /// If no collapse is left, the mesh is returned with more triangles than `target_triangles`.
+    pub fn decimate(self, target_triangles: usize) -> Mesh {
+        if self.triangle_count() <= target_triangles {
+            return self;
+        }
+
+        let mut decimator = Decimator::new(&self);
+        decimator.run(target_triangles);
+
+        let triangles: Vec<Option<Primitive>> = self.triangles.iter().zip(&decimator.triangles).map(|(original, decimated)| {
+            match (original, decimated) {
+                (Primitive::Triangle(..), Some([c0, c1, c2])) => Some(Primitive::Triangle(*c0, *c1, *c2)),
+                (Primitive::Triangle(..), None) => None,
+                (other, _) => Some(*other),
+            }
+        }).collect();
+
+        let surfaces = self.surfaces.iter().zip(&triangles)
+            .filter(|(_, triangle)| triangle.is_some())
+            .map(|(surface, _)| *surface)
+            .collect();
+
+        remove_unused(Mesh { triangles: triangles.into_iter().flatten().collect(), surfaces, quads: vec![], ..self })
+    }
+}
+
+/// A symmetric 4x4 matrix giving the weighted sum of squared distances of a point to a set of planes.
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: [f64; 3], d: f64, weight: f64) -> Quadric {
+        let [a, b, c] = normal;
+        Quadric([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|q| q * weight))
+    }
+
+    fn add(self, other: Quadric) -> Quadric {
+        let mut sum = self.0;
+        for (s, o) in sum.iter_mut().zip(other.0) {
+            *s += o;
+        }
+        Quadric(sum)
+    }
+
+    fn error(&self, [x, y, z]: [f64; 3]) -> f64 {
+        let q = &self.0;
+        q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+            + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+            + q[7] * z * z + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+/// Collapsing `from` into `to`, stamped with the versions of both nodes when the cost was computed.
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    stamps: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    // Reversed, so the binary heap pops the cheapest collapse first. Ties go to the lowest nodes, so the
+    // order doesn't depend on how the heap happens to be laid out.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then_with(|| (other.from, other.to).cmp(&(self.from, self.to)))
+    }
+}
+
+/// The triangles that a collapse removes, and the corners it moves to the kept vertex.
+struct Plan {
+    removed: Vec<usize>,
+    moved: Vec<(usize, usize, Corner)>,
+}
+
+/// The mesh seen as a graph of nodes, where a node is a group of coincident vertices.
+struct Decimator {
+    node_of: Vec<usize>,
+    positions: Vec<[f64; 3]>,
+    /// The corners of every triangle, `None` once it's removed or if the primitive isn't a triangle.
+    triangles: Vec<Option<[Corner; 3]>>,
+    /// The triangles around every node. May include triangles that have been removed since.
+    node_triangles: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    stamps: Vec<u32>,
+}
+
+impl Decimator {
+    fn new(mesh: &Mesh) -> Decimator {
+        let key = |x: f64| (x / WELD_EPSILON).round() as i64;
+
+        let mut lookup: HashMap<(i64, i64, i64), usize> = HashMap::new();
+        let mut positions: Vec<[f64; 3]> = vec![];
+        let node_of: Vec<usize> = mesh.vertices.iter().map(|v| {
+            *lookup.entry((key(v.x), key(v.y), key(v.z))).or_insert_with(|| {
+                positions.push([v.x, v.y, v.z]);
+                positions.len() - 1
+            })
+        }).collect();
+
+        let triangles: Vec<Option<[Corner; 3]>> = mesh.triangles.iter().map(|triangle| match triangle {
+            Primitive::Triangle(c0, c1, c2) => Some([*c0, *c1, *c2]),
+            _ => None,
+        }).collect();
+
+        let n_nodes = positions.len();
+        let mut node_triangles: Vec<Vec<usize>> = vec![vec![]; n_nodes];
+        let mut quadrics = vec![Quadric::default(); n_nodes];
+        for (i, corners) in triangles.iter().enumerate() {
+            let Some(corners) = corners else { continue };
+            let nodes = corners.map(|(v, _, _)| node_of[v]);
+            for node in nodes {
+                node_triangles[node].push(i);
+            }
+
+            let normal = triangle_normal(nodes.map(|node| positions[node]));
+            let area = length(normal) / 2.0;
+            if area > 0.0 {
+                let unit = scale(normal, 1.0 / length(normal));
+                let quadric = Quadric::from_plane(unit, -dot(unit, positions[nodes[0]]), area);
+                for node in nodes {
+                    quadrics[node] = quadrics[node].add(quadric);
+                }
+            }
+        }
+
+        let mut decimator = Decimator {
+            node_of,
+            positions,
+            triangles,
+            node_triangles,
+            quadrics,
+            locked: vec![false; n_nodes],
+            stamps: vec![0; n_nodes],
+        };
+
+        // Boundary edges belong to a single triangle. Their nodes are never removed, so the boundary stays as it is.
+        for node in 0..n_nodes {
+            decimator.locked[node] = decimator.neighbors(node).into_iter()
+                .any(|other| decimator.shared_triangles(node, other).len() == 1);
+        }
+
+        decimator
+    }
+
+    fn run(&mut self, target_triangles: usize) {
+        let mut n_triangles = self.triangles.iter().flatten().count();
+
+        let mut heap: BinaryHeap<Collapse> = BinaryHeap::new();
+        for node in 0..self.positions.len() {
+            self.push_collapses(&mut heap, node);
+        }
+
+        while n_triangles > target_triangles {
+            let Some(collapse) = heap.pop() else { break };
+            if collapse.stamps != (self.stamps[collapse.from], self.stamps[collapse.to]) {
+                continue;
+            }
+            let Some(plan) = self.plan(collapse.from, collapse.to) else { continue };
+
+            for t in &plan.removed {
+                self.triangles[*t] = None;
+            }
+            for (t, k, corner) in plan.moved {
+                if let Some(corners) = self.triangles[t].as_mut() {
+                    corners[k] = corner;
+                }
+                self.node_triangles[collapse.to].push(t);
+            }
+            n_triangles -= plan.removed.len();
+
+            self.quadrics[collapse.to] = self.quadrics[collapse.to].add(self.quadrics[collapse.from]);
+            self.stamps[collapse.from] += 1;
+            self.stamps[collapse.to] += 1;
+            self.push_collapses(&mut heap, collapse.to);
+        }
+    }
+
+    /// Pushes the collapses of every edge around `node`, in both directions.
+    fn push_collapses(&self, heap: &mut BinaryHeap<Collapse>, node: usize) {
+        for other in self.neighbors(node) {
+            for (from, to) in [(node, other), (other, node)] {
+                if self.locked[from] {
+                    continue;
+                }
+                heap.push(Collapse {
+                    cost: self.quadrics[from].add(self.quadrics[to]).error(self.positions[to]),
+                    from,
+                    to,
+                    stamps: (self.stamps[from], self.stamps[to]),
+                });
+            }
+        }
+    }
+
+    fn live_triangles(&self, node: usize) -> impl Iterator<Item = (usize, [Corner; 3])> + '_ {
+        let mut seen: Vec<usize> = vec![];
+        self.node_triangles[node].iter().filter_map(move |t| {
+            let corners = self.triangles[*t]?;
+            if seen.contains(t) || !corners.iter().any(|(v, _, _)| self.node_of[*v] == node) {
+                return None;
+            }
+            seen.push(*t);
+            Some((*t, corners))
+        })
+    }
+
+    fn neighbors(&self, node: usize) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self.live_triangles(node)
+            .flat_map(|(_, corners)| corners.map(|(v, _, _)| self.node_of[v]))
+            .filter(|other| *other != node)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+
+    fn shared_triangles(&self, a: usize, b: usize) -> Vec<(usize, [Corner; 3])> {
+        self.live_triangles(a)
+            .filter(|(_, corners)| corners.iter().any(|(v, _, _)| self.node_of[*v] == b))
+            .collect()
+    }
+
+    /// Checks whether `from` can be collapsed into `to`, and works out which corners move where.
+    fn plan(&self, from: usize, to: usize) -> Option<Plan> {
+        if self.locked[from] {
+            return None;
+        }
+
+        // An edge between two triangles, whose nodes have no other common neighbours, keeps the mesh manifold.
+        let shared = self.shared_triangles(from, to);
+        if shared.len() != 2 {
+            return None;
+        }
+        let mut opposite: Vec<usize> = shared.iter()
+            .flat_map(|(_, corners)| corners.map(|(v, _, _)| self.node_of[v]))
+            .filter(|node| *node != from && *node != to)
+            .collect();
+        opposite.sort_unstable();
+        let to_neighbors = self.neighbors(to);
+        let common: Vec<usize> = self.neighbors(from).into_iter().filter(|node| to_neighbors.contains(node)).collect();
+        if common != opposite {
+            return None;
+        }
+
+        let mut moved = vec![];
+        for (t, corners) in self.live_triangles(from) {
+            if shared.iter().any(|(s, _)| *s == t) {
+                continue;
+            }
+
+            let before = corners.map(|(v, _, _)| self.positions[self.node_of[v]]);
+            let mut after = before;
+            for (k, corner) in corners.iter().enumerate() {
+                if self.node_of[corner.0] != from {
+                    continue;
+                }
+                // The removed triangle with the same vertex, uv and normal at `from` tells which ones to use at `to`.
+                let replacement = shared.iter().find_map(|(_, s)| {
+                    s.contains(corner).then(|| s.iter().find(|(v, _, _)| self.node_of[*v] == to).copied())?
+                })?;
+                moved.push((t, k, replacement));
+                after[k] = self.positions[to];
+            }
+
+            let (n_before, n_after) = (triangle_normal(before), triangle_normal(after));
+            let len = length(n_before) * length(n_after);
+            if len <= 0.0 || dot(n_before, n_after) < MIN_NORMAL_COS * len {
+                return None;
+            }
+        }
+
+        Some(Plan { removed: shared.into_iter().map(|(t, _)| t).collect(), moved })
+    }
+}
+
+/// Drops the vertices, with their colors, and the uvs and normals that no triangle uses anymore.
+pub(crate) fn remove_unused(mesh: Mesh) -> Mesh {
+    let triangles: Vec<usize> = (0..mesh.triangles.len()).collect();
+    extract_triangles(&mesh, &triangles)
+}
+
+/// Creates a mesh of the given triangles of `mesh`, with only the vertices, uvs and normals they use.
+pub(crate) fn extract_triangles(mesh: &Mesh, triangles: &[usize]) -> Mesh {
+    let mut vertex_map: Vec<Option<usize>> = vec![None; mesh.vertices.len()];
+    let mut uv_map: Vec<Option<usize>> = vec![None; mesh.uv_vertices.len()];
+    let mut normal_map: Vec<Option<usize>> = vec![None; mesh.normals.len()];
+    let (mut vertices, mut uv_vertices, mut normals, mut colors) = (vec![], vec![], vec![], vec![]);
+
+    let mut remap = |(v, t, n): Corner| -> Corner {
+        let v = *vertex_map[v].get_or_insert_with(|| {
+            vertices.push(mesh.vertices[v]);
+            colors.extend(mesh.colors.get(v));
+            vertices.len() - 1
+        });
+        // Corners referring to uvs or normals that don't exist are left without them.
+        let t = t.filter(|t| *t < uv_map.len()).map(|t| *uv_map[t].get_or_insert_with(|| {
+            uv_vertices.push(mesh.uv_vertices[t]);
+            uv_vertices.len() - 1
+        }));
+        let n = n.filter(|n| *n < normal_map.len()).map(|n| *normal_map[n].get_or_insert_with(|| {
+            normals.push(mesh.normals[n]);
+            normals.len() - 1
+        }));
+        (v, t, n)
+    };
+
+    let surfaces = triangles.iter().map(|i| mesh.surfaces.get(*i).copied().unwrap_or(Surface::Front)).collect();
+    // A quad is only kept if both of its triangles are, still in a row.
+    let quads = triangles.iter().enumerate()
+        .map(|(k, i)| mesh.quads.get(*i) == Some(&true) && triangles.get(k + 1) == Some(&(i + 1)))
+        .collect();
+    let triangles = triangles.iter().map(|i| match mesh.triangles[*i] {
+        Primitive::Point(c0) => Primitive::Point(remap(c0)),
+        Primitive::Line(c0, c1) => Primitive::Line(remap(c0), remap(c1)),
+        Primitive::Triangle(c0, c1, c2) => Primitive::Triangle(remap(c0), remap(c1), remap(c2)),
+    }).collect();
+
+    Mesh { vertices, triangles, uv_vertices, normals, surfaces, colors, quads }
+}
+
+fn triangle_normal([p0, p1, p2]: [[f64; 3]; 3]) -> [f64; 3] {
+    let (e1, e2) = (sub(p1, p0), sub(p2, p0));
+    [e1[1] * e2[2] - e1[2] * e2[1], e1[2] * e2[0] - e1[0] * e2[2], e1[0] * e2[1] - e1[1] * e2[0]]
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f64; 3], s: f64) -> [f64; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn length(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_mesh_from_image, Params};
+    use obj_exporter::Vertex;
+
+    #[test]
+    fn closed_mesh_reaches_the_target() {
+        let image = image::RgbaImage::from_fn(96, 96, |x, y| {
+            let r = ((x as f32 - 48.0).powi(2) + (y as f32 - 48.0).powi(2)).sqrt();
+            image::Rgba([255, 255, 255, if r < 36.0 { 255 } else { 0 }])
+        }).into();
+        let mesh = create_mesh_from_image(&image, Params::builder().weld_seams(true).build()).unwrap();
+        let target = mesh.triangle_count() / 4;
+
+        let decimated = mesh.decimate(target);
+
+        assert!(decimated.triangle_count() <= target, "{} > {}", decimated.triangle_count(), target);
+        decimated.validate().unwrap();
+    }
+
+    #[test]
+    fn open_boundary_is_kept() {
+        // A flat 4 by 4 grid of vertices, whose 12 outer vertices lie on the open boundary.
+        let vertices: Vec<Vertex> = (0..16).map(|i| Vertex{x: (i % 4) as f64, y: (i / 4) as f64, z: 0.0}).collect();
+        let mut triangles = vec![];
+        for y in 0..3 {
+            for x in 0..3 {
+                let i = y * 4 + x;
+                triangles.push(Primitive::Triangle((i, None, None), (i + 1, None, None), (i + 5, None, None)));
+                triangles.push(Primitive::Triangle((i, None, None), (i + 5, None, None), (i + 4, None, None)));
+            }
+        }
+        let n_triangles = triangles.len();
+        let mesh = Mesh{vertices, triangles, uv_vertices: vec![], normals: vec![], surfaces: vec![Surface::Front; n_triangles], colors: vec![], quads: vec![]};
+
+        let decimated = mesh.decimate(0);
+
+        let boundary = |x: f64, y: f64| x == 0.0 || y == 0.0 || x == 3.0 || y == 3.0;
+        assert_eq!(decimated.vertices.iter().filter(|v| boundary(v.x, v.y)).count(), 12);
+        assert!(decimated.triangle_count() > 0 && decimated.triangle_count() < n_triangles);
+        decimated.validate().unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+use crate::contour::{self, MaskSource};
+use image::{DynamicImage, GenericImageView, GrayImage};
+use std::f32::consts::PI;
+
+// The luminance is blurred with this standard deviation, in pixels, so noise and dithering don't give edges.
+const BLUR_SIGMA: f32 = 1.0;
+
+// Luminance changes per pixel, of luminance in [0, 1], above which an edge is kept, or kept if it continues an
+// edge above `HIGH_THRESHOLD`.
+const HIGH_THRESHOLD: f32 = 0.1;
+const LOW_THRESHOLD: f32 = 0.05;
+
+// Pixels this close to the background are skipped, as the edge of the shape itself is not an internal edge, and
+// the blur mixes in the color of the background there.
+const SILHOUETTE_MARGIN: usize = 4;
+
+// Chains of fewer edge pixels than this are left out.
+const MIN_LENGTH: usize = 5;
+
+// How far, in pixels, the polylines may stray from the edge pixels when points are dropped.
+const SIMPLIFY_TOLERANCE: f32 = 0.5;
+
+/// Finds the strong edges in the luminance of `img` inside the shape given by `mask_source`, such as the lines
+/// of a drawing or the border between two colors, with a Canny edge detector.
+///
+/// The edges are returned as polylines in the [0, 1] image space of the contours, through the centers of the
+/// edge pixels. A polyline that closes on itself ends at its first point. Edges closer than a few pixels to the
+/// background are left out, as they belong to the outline of the shape.
+pub fn find_internal_edges(img: &DynamicImage, mask_source: MaskSource) -> Vec<Vec<[f32; 2]>> {
+    internal_edges(img, &contour::mask_from_source(img, mask_source))
+}
+
+/// Same as `find_internal_edges`, with the coverage of the shape already extracted from the image.
+pub(crate) fn internal_edges(img: &DynamicImage, mask: &GrayImage) -> Vec<Vec<[f32; 2]>> {
+    let (width, height) = img.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    if w < 3 || h < 3 {
+        return vec![];
+    }
+
+    let luminance = image::imageops::blur(&img.to_luma32f(), BLUR_SIGMA);
+    let value = |x: usize, y: usize| luminance.get_pixel(x as u32, y as u32)[0];
+
+    // Sobel gradients, scaled to the change in luminance per pixel.
+    let mut gradients = vec![[0.0f32; 2]; w * h];
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            let gx = value(x + 1, y - 1) + 2.0 * value(x + 1, y) + value(x + 1, y + 1)
+                - value(x - 1, y - 1) - 2.0 * value(x - 1, y) - value(x - 1, y + 1);
+            let gy = value(x - 1, y + 1) + 2.0 * value(x, y + 1) + value(x + 1, y + 1)
+                - value(x - 1, y - 1) - 2.0 * value(x, y - 1) - value(x + 1, y - 1);
+            gradients[y * w + x] = [gx / 8.0, gy / 8.0];
+        }
+    }
+    let magnitudes: Vec<f32> = gradients.iter().map(|[gx, gy]| (gx * gx + gy * gy).sqrt()).collect();
+
+    // Only the pixels where the gradient peaks across the edge are kept, which thins the edges to a pixel.
+    let inside = erode(mask, SILHOUETTE_MARGIN);
+    let mut strong = vec![false; w * h];
+    let mut weak = vec![false; w * h];
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            let i = y * w + x;
+            let magnitude = magnitudes[i];
+            if magnitude < LOW_THRESHOLD || !inside[i] {
+                continue;
+            }
+
+            let [gx, gy] = gradients[i];
+            let [dx, dy] = direction_offset(gy.atan2(gx));
+            let ahead = magnitudes[(y as i64 + dy) as usize * w + (x as i64 + dx) as usize];
+            let behind = magnitudes[(y as i64 - dy) as usize * w + (x as i64 - dx) as usize];
+            // Of two equally strong pixels across the edge, only one is kept.
+            if magnitude < ahead || magnitude <= behind {
+                continue;
+            }
+
+            match magnitude >= HIGH_THRESHOLD {
+                true => strong[i] = true,
+                false => weak[i] = true,
+            }
+        }
+    }
+
+    // Weak edge pixels are kept where they connect to strong ones.
+    let mut edge = strong.clone();
+    let mut stack: Vec<usize> = (0..w * h).filter(|i| strong[*i]).collect();
+    while let Some(i) = stack.pop() {
+        for n in neighbours(i, w, h) {
+            if weak[n] && !edge[n] {
+                edge[n] = true;
+                stack.push(n);
+            }
+        }
+    }
+
+    chains(&edge, w, h).into_iter()
+        .filter(|chain| chain.len() >= MIN_LENGTH)
+        .map(|chain| {
+            let points: Vec<[f32; 2]> = chain.iter().map(|i| [(i % w) as f32, (i / w) as f32]).collect();
+            simplify(&points, SIMPLIFY_TOLERANCE).into_iter()
+                .map(|[x, y]| [(x + 0.5) / width as f32, (y + 0.5) / height as f32])
+                .collect()
+        })
+        .collect()
+}
+
+/// The pixels of `mask` at or above 128 that are more than `radius` pixels from any pixel below it, counting
+/// pixels outside the mask as below, by rows and columns.
+fn erode(mask: &GrayImage, radius: usize) -> Vec<bool> {
+    let (w, h) = (mask.width() as usize, mask.height() as usize);
+    let covered: Vec<bool> = mask.pixels().map(|pixel| pixel[0] >= 128).collect();
+
+    let erode_line = |get: &dyn Fn(usize) -> bool, len: usize| -> Vec<bool> {
+        (0..len).map(|i| i >= radius && i + radius < len && (i - radius..=i + radius).all(get)).collect()
+    };
+    let mut rows = vec![false; w * h];
+    for y in 0..h {
+        let row = erode_line(&|x| covered[y * w + x], w);
+        rows[y * w..(y + 1) * w].copy_from_slice(&row);
+    }
+    let mut eroded = vec![false; w * h];
+    for x in 0..w {
+        for (y, inside) in erode_line(&|y| rows[y * w + x], h).into_iter().enumerate() {
+            eroded[y * w + x] = inside;
+        }
+    }
+    eroded
+}
+
+/// The offset to the neighbouring pixel closest to the direction `angle`, in radians.
+fn direction_offset(angle: f32) -> [i64; 2] {
+    let octant = (angle / (PI / 4.0)).round() as i64;
+    match octant.rem_euclid(8) {
+        0 => [1, 0],
+        1 => [1, 1],
+        2 => [0, 1],
+        3 => [-1, 1],
+        4 => [-1, 0],
+        5 => [-1, -1],
+        6 => [0, -1],
+        _ => [1, -1],
+    }
+}
+
+/// The pixels around pixel `i` of a `w` by `h` image, the four sharing a side first.
+fn neighbours(i: usize, w: usize, h: usize) -> impl Iterator<Item = usize> {
+    let (x, y) = ((i % w) as i64, (i / w) as i64);
+    [[1, 0], [0, 1], [-1, 0], [0, -1], [1, 1], [-1, 1], [-1, -1], [1, -1]].into_iter()
+        .map(move |[dx, dy]| (x + dx, y + dy))
+        .filter(move |(x, y)| *x >= 0 && *y >= 0 && *x < w as i64 && *y < h as i64)
+        .map(move |(x, y)| y as usize * w + x as usize)
+}
+
+/// Splits the edge pixels into chains of neighbouring pixels. Chains start at pixels with a single neighbour,
+/// so open edges come out in one piece, and the closed loops that are left start anywhere. Branches become chains
+/// of their own, and a chain that gets back next to where it started ends at its first pixel.
+fn chains(edge: &[bool], w: usize, h: usize) -> Vec<Vec<usize>> {
+    let mut visited = vec![false; edge.len()];
+    let mut chains = vec![];
+
+    let ends: Vec<usize> = (0..edge.len())
+        .filter(|i| edge[*i] && neighbours(*i, w, h).filter(|n| edge[*n]).count() == 1)
+        .collect();
+    for start in ends.into_iter().chain(0..edge.len()) {
+        if !edge[start] || visited[start] {
+            continue;
+        }
+
+        let mut chain = vec![start];
+        visited[start] = true;
+        let mut current = start;
+        while let Some(next) = neighbours(current, w, h).find(|n| edge[*n] && !visited[*n]) {
+            visited[next] = true;
+            chain.push(next);
+            current = next;
+        }
+
+        if chain.len() > 2 && neighbours(current, w, h).any(|n| n == start) {
+            chain.push(start);
+        }
+        chains.push(chain);
+    }
+    chains
+}
+
+/// Drops the points of an open polyline that lie within `tolerance` of the line through the points kept around
+/// them, with `contour::rdp_mark`. The first and last points are always kept.
+fn simplify(points: &[[f32; 2]], tolerance: f32) -> Vec<[f32; 2]> {
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let indices: Vec<usize> = (0..points.len()).collect();
+    contour::rdp_mark(points, &indices, tolerance, &mut keep);
+
+    points.iter().zip(keep).filter(|(_, keep)| *keep).map(|(p, _)| *p).collect()
+}
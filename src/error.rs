@@ -0,0 +1,92 @@
+use std::fmt;
+
+/// Errors that can occur when creating or saving a mesh.
+#[derive(Debug)]
+pub enum MeshError {
+    /// No boundary between the shape and the background was found in the image.
+    NoContourFound,
+    /// The whole image is transparent, or outside the shape for the `contour::MaskSource` in use,
+    /// so there is no shape to trace.
+    FullyTransparent,
+    /// The whole image is opaque, or inside the shape for the `contour::MaskSource` in use, and
+    /// `contour::Params::pad_border` is 0, so the shape has no edge to trace.
+    FullyOpaque,
+    /// Contours were found, but all of them enclose less than `contour::Params::min_area`.
+    NoSignificantContour,
+    /// A contour was started but the tracer never got back to its starting point. The tracer itself gives
+    /// `ContourTooComplex` when it runs out of steps.
+    ContourNotClosed,
+    /// Tracing a contour took more steps than `contour::Params::max_trace_steps` allows, or than the image has
+    /// room for if it's `None`, after finding the given number of points.
+    ContourTooComplex { points: usize },
+    /// The image is too small to trace a contour in.
+    ImageTooSmall,
+    /// The contour with the given number of points, holes included, could not be triangulated,
+    /// or the triangulation left parts of it uncovered.
+    TriangulationFailed { points: usize },
+    /// The outer contour or a hole crosses or touches itself, so its caps can't be triangulated without overlaps.
+    SelfIntersectingContour,
+    /// The contour isn't a valid polygon, e.g. because it intersects itself, has too few points or has a coordinate
+    /// that isn't finite.
+    InvalidPolygon,
+    /// The mesh has too many triangles or vertices for the output format.
+    MeshTooLarge,
+    /// A triangle refers to a vertex, uv or normal that doesn't exist.
+    IndexOutOfBounds { triangle: usize },
+    /// A triangle has zero area.
+    DegenerateTriangle { triangle: usize },
+    /// The file path can't be used for the requested output.
+    InvalidPath,
+    /// A line of an OBJ file can't be read, counting from 1.
+    InvalidObj { line: usize },
+    /// Reading or writing a file failed.
+    Io(std::io::Error),
+    /// Decoding or encoding an image failed.
+    Image(image::ImageError),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::NoContourFound => write!(f, "no contour found (is the shape too faint to reach the threshold?)"),
+            MeshError::FullyTransparent => write!(f, "the image is fully transparent, so there is no shape to trace"),
+            MeshError::FullyOpaque => write!(f, "the image is fully opaque, so the shape has no edge to trace (for images without transparency, use a luminance or color key mask, or pad the border)"),
+            MeshError::NoSignificantContour => write!(f, "every contour found in the image is smaller than the minimum area"),
+            MeshError::ContourNotClosed => write!(f, "contour tracing failed: the contour could not be closed"),
+            MeshError::ContourTooComplex { points } => write!(f, "contour tracing failed: gave up after {} points", points),
+            MeshError::ImageTooSmall => write!(f, "the image is too small to find a contour in"),
+            MeshError::TriangulationFailed { points } => write!(f, "triangulation failed: {}-point polygon", points),
+            MeshError::SelfIntersectingContour => write!(f, "the contour intersects itself"),
+            MeshError::InvalidPolygon => write!(f, "the contour is not a valid polygon"),
+            MeshError::MeshTooLarge => write!(f, "the mesh is too large for the output format"),
+            MeshError::IndexOutOfBounds { triangle } => write!(f, "triangle {} refers to a vertex, uv or normal that doesn't exist", triangle),
+            MeshError::DegenerateTriangle { triangle } => write!(f, "triangle {} has zero area", triangle),
+            MeshError::InvalidPath => write!(f, "invalid file path"),
+            MeshError::InvalidObj { line } => write!(f, "line {} of the OBJ file can't be read", line),
+            MeshError::Io(e) => write!(f, "i/o error: {}", e),
+            MeshError::Image(e) => write!(f, "image error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MeshError::Io(e) => Some(e),
+            MeshError::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for MeshError {
+    fn from(e: std::io::Error) -> Self {
+        MeshError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for MeshError {
+    fn from(e: image::ImageError) -> Self {
+        MeshError::Image(e)
+    }
+}
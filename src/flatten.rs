@@ -0,0 +1,63 @@
+use crate::{Mesh, MeshError};
+use obj_exporter::Primitive;
+use std::collections::HashMap;
+
+/// A mesh where every vertex has exactly one position, normal and uv, addressed by a single index.
+///
+/// `Mesh` triangles reference positions, uvs and normals through separate indices, like OBJ does.
+/// Most other formats only support one index per vertex, so every distinct
+/// `(vertex, uv, normal)` index triple used by a triangle corner becomes its own vertex here.
+pub(crate) struct FlatMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+    /// Whether every triangle corner referenced a normal.
+    pub has_normals: bool,
+    /// Whether every triangle corner referenced a uv.
+    pub has_uvs: bool,
+}
+
+impl FlatMesh {
+    /// Flattens the triangles of `mesh`. Corners whose uv or normal doesn't exist are taken as having none, and a
+    /// triangle whose vertex doesn't exist gives `MeshError::IndexOutOfBounds`.
+    pub fn from_mesh(mesh: &Mesh) -> Result<FlatMesh, MeshError> {
+        let mut flat = FlatMesh {
+            positions: vec![],
+            normals: vec![],
+            uvs: vec![],
+            indices: vec![],
+            has_normals: true,
+            has_uvs: true,
+        };
+        let mut lookup: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+
+        for (i, triangle) in mesh.triangles.iter().enumerate() {
+            let Primitive::Triangle(c0, c1, c2) = triangle else { continue };
+
+            for (v, t, n) in [*c0, *c1, *c2] {
+                let p = mesh.vertices.get(v).ok_or(MeshError::IndexOutOfBounds { triangle: i })?;
+                let t = t.filter(|t| *t < mesh.uv_vertices.len());
+                let n = n.filter(|n| *n < mesh.normals.len());
+                flat.has_uvs &= t.is_some();
+                flat.has_normals &= n.is_some();
+
+                let index = *lookup.entry((v, t, n)).or_insert_with(|| {
+                    flat.positions.push([p.x as f32, p.y as f32, p.z as f32]);
+                    flat.normals.push(n.map_or([0.0; 3], |n| {
+                        let n = &mesh.normals[n];
+                        [n.x as f32, n.y as f32, n.z as f32]
+                    }));
+                    flat.uvs.push(t.map_or([0.0; 2], |t| {
+                        let t = &mesh.uv_vertices[t];
+                        [t.u as f32, t.v as f32]
+                    }));
+                    (flat.positions.len() - 1) as u32
+                });
+                flat.indices.push(index);
+            }
+        }
+
+        Ok(flat)
+    }
+}
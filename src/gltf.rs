@@ -0,0 +1,168 @@
+use crate::flatten::FlatMesh;
+use crate::{Mesh, MeshError};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const COMPONENT_FLOAT: u32 = 5126;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+
+// position (3 floats) + normal (3 floats) + uv (2 floats)
+const VERTEX_STRIDE: usize = 32;
+
+/// Saves a mesh to a self-contained binary glTF (`.glb`) file.
+///
+/// The geometry is written as one interleaved position/normal/uv buffer, and `texture_png` is
+/// embedded as the base color texture of a single material. If `texture_png` is empty, the
+/// material is written without a texture.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to save.
+/// * `texture_png` - The PNG encoded texture to embed.
+/// * `file_path` - The file path to save the mesh to.
+///
+/// # Returns
+///
+/// A `Result` which is `Ok` if the mesh was saved successfully, or an `Err` containing a `MeshError`.
+pub fn save_mesh_to_glb(mesh: &Mesh, texture_png: &[u8], file_path: impl AsRef<Path>) -> Result<(), MeshError> {
+    let flat = FlatMesh::from_mesh(mesh)?;
+    let n_vertices = flat.positions.len();
+
+    let mut bin: Vec<u8> = Vec::with_capacity(n_vertices * VERTEX_STRIDE + flat.indices.len() * 4 + texture_png.len());
+
+    for i in 0..n_vertices {
+        let [u, v] = flat.uvs[i];
+        // OBJ uvs have their origin in the bottom left corner, glTF uvs in the top left corner.
+        let values = flat.positions[i].into_iter().chain(flat.normals[i]).chain([u, 1.0 - v]);
+        for value in values {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    let vertex_view_length = bin.len();
+
+    let index_view_offset = bin.len();
+    for index in &flat.indices {
+        bin.extend_from_slice(&index.to_le_bytes());
+    }
+    let index_view_length = bin.len() - index_view_offset;
+
+    let image_view_offset = bin.len();
+    bin.extend_from_slice(texture_png);
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+
+    let (min, max) = position_bounds(&flat.positions);
+
+    let mut attributes = vec!["\"POSITION\":0".to_string()];
+    if flat.has_normals {
+        attributes.push("\"NORMAL\":1".to_string());
+    }
+    if flat.has_uvs {
+        attributes.push("\"TEXCOORD_0\":2".to_string());
+    }
+
+    let mut buffer_views = vec![
+        format!(
+            "{{\"buffer\":0,\"byteOffset\":0,\"byteLength\":{},\"byteStride\":{},\"target\":{}}}",
+            vertex_view_length, VERTEX_STRIDE, TARGET_ARRAY_BUFFER
+        ),
+        format!(
+            "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{}}}",
+            index_view_offset, index_view_length, TARGET_ELEMENT_ARRAY_BUFFER
+        ),
+    ];
+
+    let accessors = [
+        format!(
+            "{{\"bufferView\":0,\"byteOffset\":0,\"componentType\":{},\"count\":{},\"type\":\"VEC3\",\"min\":[{},{},{}],\"max\":[{},{},{}]}}",
+            COMPONENT_FLOAT, n_vertices, min[0], min[1], min[2], max[0], max[1], max[2]
+        ),
+        format!(
+            "{{\"bufferView\":0,\"byteOffset\":12,\"componentType\":{},\"count\":{},\"type\":\"VEC3\"}}",
+            COMPONENT_FLOAT, n_vertices
+        ),
+        format!(
+            "{{\"bufferView\":0,\"byteOffset\":24,\"componentType\":{},\"count\":{},\"type\":\"VEC2\"}}",
+            COMPONENT_FLOAT, n_vertices
+        ),
+        format!(
+            "{{\"bufferView\":1,\"byteOffset\":0,\"componentType\":{},\"count\":{},\"type\":\"SCALAR\"}}",
+            COMPONENT_UNSIGNED_INT, flat.indices.len()
+        ),
+    ];
+
+    let mut json = format!(
+        "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"image_to_mesh\"}},\"scene\":0,\"scenes\":[{{\"nodes\":[0]}}],\"nodes\":[{{\"mesh\":0}}],\"meshes\":[{{\"primitives\":[{{\"attributes\":{{{}}},\"indices\":3,\"material\":0}}]}}],\"accessors\":[{}],",
+        attributes.join(","),
+        accessors.join(",")
+    );
+
+    if texture_png.is_empty() {
+        json.push_str("\"materials\":[{\"pbrMetallicRoughness\":{\"metallicFactor\":0.0}}],");
+    } else {
+        buffer_views.push(format!(
+            "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{}}}",
+            image_view_offset,
+            texture_png.len()
+        ));
+        json.push_str("\"materials\":[{\"pbrMetallicRoughness\":{\"baseColorTexture\":{\"index\":0},\"metallicFactor\":0.0}}],");
+        json.push_str("\"textures\":[{\"sampler\":0,\"source\":0}],\"samplers\":[{}],");
+        json.push_str("\"images\":[{\"bufferView\":2,\"mimeType\":\"image/png\"}],");
+    }
+
+    json.push_str(&format!(
+        "\"bufferViews\":[{}],\"buffers\":[{{\"byteLength\":{}}}]}}",
+        buffer_views.join(","),
+        bin.len()
+    ));
+
+    let mut json = json.into_bytes();
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+
+    let total_length = 12 + 8 + json.len() + 8 + bin.len();
+
+    let mut file = BufWriter::new(File::create(file_path)?);
+    file.write_all(&GLB_MAGIC.to_le_bytes())?;
+    file.write_all(&GLB_VERSION.to_le_bytes())?;
+    file.write_all(&u32::try_from(total_length).map_err(|_| MeshError::MeshTooLarge)?.to_le_bytes())?;
+
+    file.write_all(&u32::try_from(json.len()).map_err(|_| MeshError::MeshTooLarge)?.to_le_bytes())?;
+    file.write_all(&CHUNK_JSON.to_le_bytes())?;
+    file.write_all(&json)?;
+
+    file.write_all(&u32::try_from(bin.len()).map_err(|_| MeshError::MeshTooLarge)?.to_le_bytes())?;
+    file.write_all(&CHUNK_BIN.to_le_bytes())?;
+    file.write_all(&bin)?;
+
+    file.flush()?;
+    Ok(())
+}
+
+fn position_bounds(positions: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+
+    for p in positions {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+
+    if positions.is_empty() {
+        return ([0.0; 3], [0.0; 3]);
+    }
+
+    (min, max)
+}
@@ -1,13 +1,57 @@
 /// This module contains functions and structures for creating and saving 3D meshes from images.
 pub mod contour;
+#[cfg(feature = "io")]
+mod dae;
+mod decimate;
+pub mod edges;
+mod error;
+#[cfg(feature = "io")]
+mod flatten;
+#[cfg(feature = "io")]
+mod gltf;
+mod marching_squares;
+mod obj;
+#[cfg(feature = "io")]
+mod ply;
+mod progress;
+#[cfg(feature = "io")]
+mod stl;
+mod tangents;
+#[cfg(feature = "io")]
+mod threemf;
+#[cfg(feature = "io")]
+mod zip;
 
-use contour::find_contour_from_transparency_with_offset;
-use obj_exporter::{Geometry, ObjSet, Object, Primitive, Shape, TVertex, Vertex};
-use image::DynamicImage;
-use std::{error::Error, vec};
+pub use contour::find_contour_from_transparency_with_offset;
+use contour::{Contour, Outline};
+use obj_exporter::{Primitive, TVertex, Vertex};
+use image::{DynamicImage, GenericImageView, GrayImage, Pixel};
+use std::{collections::HashMap, ops::Range, vec};
+#[cfg(feature = "io")]
+use std::path::Path;
 use rgeometry::{data::Polygon, data::Point};
-use std::fs::File;
-use std::io::{Read, Write};
+
+#[cfg(feature = "io")]
+pub use dae::save_mesh_to_dae;
+pub use error::MeshError;
+#[cfg(feature = "io")]
+pub use gltf::save_mesh_to_glb;
+pub use obj::{read_mesh_obj, write_mesh_obj, write_meshes_obj};
+#[cfg(feature = "io")]
+pub use obj::{
+    load_mesh_from_obj, save_mesh_to_file, save_mesh_to_file_with_materials, save_mesh_to_file_with_options,
+    save_meshes_to_file,
+};
+#[cfg(feature = "io")]
+pub use ply::save_mesh_to_ply;
+pub use progress::Progress;
+#[cfg(feature = "io")]
+pub use stl::save_mesh_to_stl;
+pub use tangents::compute_tangents;
+#[cfg(feature = "io")]
+pub use threemf::save_mesh_to_3mf;
+#[cfg(feature = "io")]
+pub use zip::save_mesh_to_zip;
 
 /// Represents a 3D mesh with vertices, triangles, UV vertices, and normals.
 pub struct Mesh {
@@ -15,94 +59,1968 @@ pub struct Mesh {
     pub triangles: Vec<Primitive>,
     pub uv_vertices: Vec<TVertex>,
     pub normals: Vec<Vertex>,
+    /// The part of the mesh each triangle belongs to, in the same order as `triangles`.
+    /// Triangles without a surface are treated as part of the front.
+    pub surfaces: Vec<Surface>,
+    /// The color of every vertex, in the same order as `vertices`. Empty if the mesh has no vertex colors.
+    pub colors: Vec<[u8; 3]>,
+    /// Whether each triangle forms a quad with the triangle after it, in the same order as `triangles`, see
+    /// `CapTopology::QuadDominant`. OBJ files write such a pair as one quad face if the triangles share the
+    /// corners of an edge, other formats write the two triangles. Triangles past the end don't form quads.
+    pub quads: Vec<bool>,
+}
+
+/// An edge of a mesh that isn't shared by exactly two triangles, see `Mesh::manifold_report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonManifoldEdge {
+    /// The indices into `Mesh::vertices` of the two ends of the edge.
+    pub vertices: [usize; 2],
+    /// The indices into `Mesh::triangles` of the triangles that share the edge.
+    pub triangles: Vec<usize>,
+}
+
+/// The parts of a mesh, which can be given different materials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Surface {
+    Front,
+    Back,
+    Side,
+}
+
+// Vertices closer than this are merged when welding seams.
+const WELD_EPSILON: f64 = 1e-6;
+
+// Consecutive contour points closer than this, in the [0, 1] image space, are merged before triangulation.
+const DEDUP_EPSILON: f32 = 1e-6;
+
+// Contour points where the contour turns by less than this, in radians, are triangulated as part of a straight edge.
+const STRAIGHT_EPSILON: f32 = 1e-4;
+
+impl Mesh {
+    /// Merges vertices whose positions are within `epsilon` of each other, and gives every remaining
+    /// vertex a single normal averaged from all normals its triangles used.
+    ///
+    /// Positions are compared by snapping them to a grid with a cell size of `epsilon`.
+    /// UVs are left untouched, so texture seams are preserved.
+    pub fn weld_seams(self, epsilon: f64) -> Mesh {
+        let key = |v: &Vertex| ((v.x / epsilon).round() as i64, (v.y / epsilon).round() as i64, (v.z / epsilon).round() as i64);
+
+        let mut lookup: HashMap<(i64, i64, i64), usize> = HashMap::new();
+        let mut vertices: Vec<Vertex> = vec![];
+        let mut colors: Vec<[u8; 3]> = vec![];
+        let remap: Vec<usize> = self.vertices.iter().enumerate().map(|(i, v)| {
+            *lookup.entry(key(v)).or_insert_with(|| {
+                vertices.push(*v);
+                // A welded vertex takes the color of the first vertex merged into it.
+                colors.extend(self.colors.get(i));
+                vertices.len() - 1
+            })
+        }).collect();
+
+        // Each distinct normal used at a welded vertex contributes once, however many triangles use it.
+        let mut used_normals: Vec<Vec<usize>> = vec![vec![]; vertices.len()];
+        for triangle in &self.triangles {
+            let Primitive::Triangle(c0, c1, c2) = triangle else { continue };
+            for (v, _, n) in [c0, c1, c2] {
+                if let Some(n) = n.filter(|n| *n < self.normals.len()) {
+                    if !used_normals[remap[*v]].contains(&n) {
+                        used_normals[remap[*v]].push(n);
+                    }
+                }
+            }
+        }
+
+        let normals: Vec<Vertex> = used_normals.iter().map(|used| {
+            let sum = used.iter().fold([0.0; 3], |acc, n| {
+                let n = &self.normals[*n];
+                [acc[0] + n.x, acc[1] + n.y, acc[2] + n.z]
+            });
+            let len = (sum[0] * sum[0] + sum[1] * sum[1] + sum[2] * sum[2]).sqrt();
+            if len == 0.0 {
+                Vertex{x: 0.0, y: 0.0, z: 0.0}
+            } else {
+                Vertex{x: sum[0] / len, y: sum[1] / len, z: sum[2] / len}
+            }
+        }).collect();
+
+        let has_normals = !self.normals.is_empty();
+        let weld = |(v, t, _): (usize, Option<usize>, Option<usize>)| {
+            (remap[v], t, if has_normals { Some(remap[v]) } else { None })
+        };
+        // Points and lines keep their uvs and normals, as OBJ files can't give lines a normal.
+        let weld_position = |(v, t, n): (usize, Option<usize>, Option<usize>)| (remap[v], t, n);
+        let triangles = self.triangles.iter().map(|triangle| match *triangle {
+            Primitive::Point(c0) => Primitive::Point(weld_position(c0)),
+            Primitive::Line(c0, c1) => Primitive::Line(weld_position(c0), weld_position(c1)),
+            Primitive::Triangle(c0, c1, c2) => Primitive::Triangle(weld(c0), weld(c1), weld(c2)),
+        }).collect();
+
+        Mesh {
+            vertices,
+            triangles,
+            uv_vertices: self.uv_vertices,
+            normals: if has_normals { normals } else { vec![] },
+            surfaces: self.surfaces,
+            colors,
+            quads: self.quads,
+        }
+    }
+
+    /// Gives every triangle its own three vertices, with the face normal of the triangle at all of them,
+    /// for hard faceted shading. This is the opposite of `weld_seams`.
+    ///
+    /// The uvs and colors are copied to the new vertices, so the texture is unchanged. Triangles with zero area
+    /// get a zero normal. Points and lines also get their own vertices, without normals.
+    pub fn to_flat_shaded(self) -> Mesh {
+        let mut mesh = Mesh{vertices: vec![], triangles: vec![], uv_vertices: vec![], normals: vec![], surfaces: self.surfaces, colors: vec![], quads: self.quads};
+        let has_colors = self.colors.len() == self.vertices.len();
+
+        for primitive in &self.triangles {
+            let corners = match *primitive {
+                Primitive::Point(c0) => vec![c0],
+                Primitive::Line(c0, c1) => vec![c0, c1],
+                Primitive::Triangle(c0, c1, c2) => vec![c0, c1, c2],
+            };
+
+            let normal = match corners.as_slice() {
+                [c0, c1, c2] => {
+                    let [v0, v1, v2] = [c0.0, c1.0, c2.0].map(|v| self.vertices[v]);
+                    let e1 = [v1.x - v0.x, v1.y - v0.y, v1.z - v0.z];
+                    let e2 = [v2.x - v0.x, v2.y - v0.y, v2.z - v0.z];
+                    let cross = [e1[1] * e2[2] - e1[2] * e2[1], e1[2] * e2[0] - e1[0] * e2[2], e1[0] * e2[1] - e1[1] * e2[0]];
+                    let len = (cross[0] * cross[0] + cross[1] * cross[1] + cross[2] * cross[2]).sqrt();
+                    mesh.normals.push(match len {
+                        0.0 => Vertex{x: 0.0, y: 0.0, z: 0.0},
+                        _ => Vertex{x: cross[0] / len, y: cross[1] / len, z: cross[2] / len},
+                    });
+                    Some(mesh.normals.len() - 1)
+                }
+                _ => None,
+            };
+
+            let corners: Vec<(usize, Option<usize>, Option<usize>)> = corners.into_iter().map(|(v, t, _)| {
+                mesh.vertices.push(self.vertices[v]);
+                if has_colors {
+                    mesh.colors.push(self.colors[v]);
+                }
+                let t = t.and_then(|t| self.uv_vertices.get(t)).map(|uv| {
+                    mesh.uv_vertices.push(*uv);
+                    mesh.uv_vertices.len() - 1
+                });
+                (mesh.vertices.len() - 1, t, normal)
+            }).collect();
+
+            mesh.triangles.push(match corners.as_slice() {
+                [c0] => Primitive::Point(*c0),
+                [c0, c1] => Primitive::Line(*c0, *c1),
+                [c0, c1, c2] => Primitive::Triangle(*c0, *c1, *c2),
+                _ => unreachable!(),
+            });
+        }
+
+        mesh
+    }
+
+    /// Adds the vertices, triangles, uvs and normals of `other` to this mesh. The corners of its points, lines
+    /// and triangles are moved past the vertices, uvs and normals already in this mesh.
+    pub fn append(&mut self, other: Mesh) {
+        let (n_vertices, n_uvs, n_normals) = (self.vertices.len(), self.uv_vertices.len(), self.normals.len());
+        let other_triangles = other.triangles.len();
+        let offset = |(v, t, n): (usize, Option<usize>, Option<usize>)| (v + n_vertices, t.map(|t| t + n_uvs), n.map(|n| n + n_normals));
+
+        self.triangles.extend(other.triangles.into_iter().map(|triangle| match triangle {
+            Primitive::Point(c0) => Primitive::Point(offset(c0)),
+            Primitive::Line(c0, c1) => Primitive::Line(offset(c0), offset(c1)),
+            Primitive::Triangle(c0, c1, c2) => Primitive::Triangle(offset(c0), offset(c1), offset(c2)),
+        }));
+        // Vertex colors are only kept if both meshes have them.
+        if self.colors.len() == n_vertices && other.colors.len() == other.vertices.len() {
+            self.colors.extend(other.colors);
+        } else {
+            self.colors.clear();
+        }
+        self.vertices.extend(other.vertices);
+        self.uv_vertices.extend(other.uv_vertices);
+        self.normals.extend(other.normals);
+        // Keeps the surfaces of `other` lined up with its triangles, even if this mesh is missing some.
+        self.surfaces.resize(self.triangles.len() - other_triangles, Surface::Front);
+        self.surfaces.extend(other.surfaces);
+        self.quads.resize(self.triangles.len() - other_triangles, false);
+        self.quads.extend(other.quads);
+    }
+
+    /// Combines the meshes into one, in the order given, like appending each of them to an empty mesh.
+    /// Vertex colors are only kept if every mesh has them.
+    pub fn merge(meshes: Vec<Mesh>) -> Mesh {
+        let mut merged = Mesh{vertices: vec![], triangles: vec![], uv_vertices: vec![], normals: vec![], surfaces: vec![], colors: vec![], quads: vec![]};
+        for mesh in meshes {
+            merged.append(mesh);
+        }
+        merged
+    }
+
+    /// Reverses the winding of every triangle and negates every normal, so the mesh faces the other way
+    /// while its shading stays consistent with the geometry. Flipping twice gives back the original mesh.
+    pub fn flip_winding(mut self) -> Mesh {
+        for triangle in self.triangles.iter_mut() {
+            if let Primitive::Triangle(c0, c1, c2) = *triangle {
+                *triangle = Primitive::Triangle(c0, c2, c1);
+            }
+        }
+        for n in self.normals.iter_mut() {
+            *n = Vertex{x: -n.x, y: -n.y, z: -n.z};
+        }
+
+        self
+    }
+
+    /// Returns the minimum and maximum corner of the axis-aligned bounding box of the vertices.
+    /// A mesh without vertices has an empty box at the origin.
+    pub fn bounding_box(&self) -> ([f64; 3], [f64; 3]) {
+        if self.vertices.is_empty() {
+            return ([0.0; 3], [0.0; 3]);
+        }
+
+        self.vertices.iter().fold(([f64::MAX; 3], [f64::MIN; 3]), |(min, max), v| (
+            [min[0].min(v.x), min[1].min(v.y), min[2].min(v.z)],
+            [max[0].max(v.x), max[1].max(v.y), max[2].max(v.z)],
+        ))
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.iter().filter(|t| matches!(t, Primitive::Triangle(..))).count()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    /// Whether every edge is shared by exactly two triangles, so the mesh is closed and manifold.
+    /// See `manifold_report`.
+    pub fn is_watertight(&self) -> bool {
+        self.manifold_report().is_empty()
+    }
+
+    /// Returns every edge that isn't shared by exactly two triangles, sorted by vertex index.
+    ///
+    /// Vertices within `WELD_EPSILON` of each other count as one, as the caps and side walls have their own copies
+    /// of the vertices they meet at. Triangles referring to vertices that don't exist are skipped, see `validate`.
+    pub fn manifold_report(&self) -> Vec<NonManifoldEdge> {
+        let key = |v: &Vertex| ((v.x / WELD_EPSILON).round() as i64, (v.y / WELD_EPSILON).round() as i64, (v.z / WELD_EPSILON).round() as i64);
+
+        // The first vertex at every position stands for all vertices there.
+        let mut lookup: HashMap<(i64, i64, i64), usize> = HashMap::new();
+        let welded: Vec<usize> = self.vertices.iter().enumerate().map(|(i, v)| *lookup.entry(key(v)).or_insert(i)).collect();
+
+        let mut edges: HashMap<[usize; 2], Vec<usize>> = HashMap::new();
+        for (i, triangle) in self.triangles.iter().enumerate() {
+            let Primitive::Triangle(c0, c1, c2) = triangle else { continue };
+            let Some(corners) = [c0.0, c1.0, c2.0].into_iter().map(|v| welded.get(v).copied()).collect::<Option<Vec<_>>>() else { continue };
+
+            for k in 0..3 {
+                let (a, b) = (corners[k], corners[(k + 1) % 3]);
+                if a != b {
+                    edges.entry([a.min(b), a.max(b)]).or_default().push(i);
+                }
+            }
+        }
+
+        let mut report: Vec<NonManifoldEdge> = edges.into_iter()
+            .filter(|(_, triangles)| triangles.len() != 2)
+            .map(|(vertices, triangles)| NonManifoldEdge { vertices, triangles })
+            .collect();
+        report.sort_by_key(|edge| edge.vertices);
+        report
+    }
+
+    /// Splits the mesh into its connected parts, e.g. one per shape with `Params::all_shapes`, ordered by their
+    /// first triangle. Vertices within `WELD_EPSILON` of each other connect their triangles, like in
+    /// `manifold_report`. Triangles referring to vertices that don't exist are left out.
+    pub fn split_islands(&self) -> Vec<Mesh> {
+        let key = |v: &Vertex| ((v.x / WELD_EPSILON).round() as i64, (v.y / WELD_EPSILON).round() as i64, (v.z / WELD_EPSILON).round() as i64);
+
+        let mut lookup: HashMap<(i64, i64, i64), usize> = HashMap::new();
+        let welded: Vec<usize> = self.vertices.iter().enumerate().map(|(i, v)| *lookup.entry(key(v)).or_insert(i)).collect();
+
+        // Union-find over the welded vertices, every vertex pointing towards the root of its island.
+        let mut parent: Vec<usize> = (0..self.vertices.len()).collect();
+        let find = |parent: &mut Vec<usize>, mut i: usize| {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        };
+
+        let corners: Vec<Option<Vec<usize>>> = self.triangles.iter().map(|primitive| {
+            let corners = match *primitive {
+                Primitive::Point(c0) => vec![c0.0],
+                Primitive::Line(c0, c1) => vec![c0.0, c1.0],
+                Primitive::Triangle(c0, c1, c2) => vec![c0.0, c1.0, c2.0],
+            };
+            corners.into_iter().map(|v| welded.get(v).copied()).collect()
+        }).collect();
+
+        for corners in corners.iter().flatten() {
+            let root = find(&mut parent, corners[0]);
+            for v in &corners[1..] {
+                let other = find(&mut parent, *v);
+                parent[other] = root;
+            }
+        }
+
+        let mut island_of_root: HashMap<usize, usize> = HashMap::new();
+        let mut islands: Vec<Vec<usize>> = vec![];
+        for (i, corners) in corners.iter().enumerate() {
+            let Some(corners) = corners else { continue };
+            let root = find(&mut parent, corners[0]);
+            let island = *island_of_root.entry(root).or_insert_with(|| {
+                islands.push(vec![]);
+                islands.len() - 1
+            });
+            islands[island].push(i);
+        }
+
+        islands.iter().map(|triangles| decimate::extract_triangles(self, triangles)).collect()
+    }
+
+    /// Checks that every triangle refers to existing vertices, uvs and normals, and has a non-zero area.
+    pub fn validate(&self) -> Result<(), MeshError> {
+        for (i, triangle) in self.triangles.iter().enumerate() {
+            let Primitive::Triangle(c0, c1, c2) = triangle else { continue };
+
+            let attributes_exist = [c0, c1, c2].iter().all(|(_, t, n)| {
+                t.is_none_or(|t| t < self.uv_vertices.len()) && n.is_none_or(|n| n < self.normals.len())
+            });
+            let [v0, v1, v2] = [c0.0, c1.0, c2.0].map(|v| self.vertices.get(v));
+            let (Some(v0), Some(v1), Some(v2), true) = (v0, v1, v2, attributes_exist) else {
+                return Err(MeshError::IndexOutOfBounds { triangle: i });
+            };
+
+            let e1 = [v1.x - v0.x, v1.y - v0.y, v1.z - v0.z];
+            let e2 = [v2.x - v0.x, v2.y - v0.y, v2.z - v0.z];
+            let cross = [e1[1] * e2[2] - e1[2] * e2[1], e1[2] * e2[0] - e1[0] * e2[2], e1[0] * e2[1] - e1[1] * e2[0]];
+            if cross == [0.0; 3] {
+                return Err(MeshError::DegenerateTriangle { triangle: i });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Translates, and optionally scales, the vertices according to `mode`, based on their axis-aligned bounding box.
+    ///
+    /// Normals are left untouched, as the scaling is uniform.
+    pub fn normalize(mut self, mode: NormalizeMode) -> Mesh {
+        if mode == NormalizeMode::None || self.vertices.is_empty() {
+            return self;
+        }
+
+        let (min, max) = self.bounding_box();
+        let center = [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0, (min[2] + max[2]) / 2.0];
+
+        let largest_dimension = (0..3).map(|i| max[i] - min[i]).fold(0.0, f64::max);
+        let scale = match mode {
+            NormalizeMode::FitUnitCube if largest_dimension > 0.0 => 1.0 / largest_dimension,
+            _ => 1.0,
+        };
+
+        for v in self.vertices.iter_mut() {
+            v.x = (v.x - center[0]) * scale;
+            v.y = (v.y - center[1]) * scale;
+            v.z = (v.z - center[2]) * scale;
+        }
+
+        self
+    }
+}
+
+/// Parameters for creating a mesh, including contour parameters, thickness, and whether to include UVs.
+///
+/// Besides `Params::default()`, `print_quality`, `game_asset` and `high_fidelity` give starting points for
+/// common uses.
+#[derive(Debug, Clone)]
+pub struct Params {
+    pub contour_params: contour::Params,
+    pub thickness: ThicknessMode,
+    pub extrude_mode: ExtrudeMode,
+    pub style: MeshStyle,
+    pub include_uvs: bool,
+    /// Whether the mesh has normals. Without them, the OBJ faces are written as `v/vt` and files are smaller,
+    /// but importers have to compute the shading themselves.
+    pub include_normals: bool,
+    pub uv_mode: UvMode,
+    /// Pixels added around the bounding box with `UvMode::BoundingBox`, so that texture filtering at the edge of
+    /// the shape samples the image around it instead of whatever lies past the edge of a cropped texture.
+    pub texture_bleed: u32,
+    /// Mirrors the uvs of the back horizontally, so the texture reads correctly when the mesh is seen from behind.
+    pub mirror_back_uvs: bool,
+    /// Merges coincident vertices and averages their normals, giving smooth shading across the cap/side seam.
+    pub weld_seams: bool,
+    /// Whether the back is closed by a cap. Without it, the mesh is an open shell of the front and the side walls,
+    /// which isn't watertight.
+    pub close_back: bool,
+    /// Rounds the edges between the caps and the side walls. `None` gives straight walls.
+    pub bevel: Option<BevelParams>,
+    /// Rings inserted along z between the front and the back of the side walls, giving `wall_subdivisions + 1`
+    /// rows of quads so the walls bend smoothly when the mesh is deformed. With a bevel, every bevel segment is
+    /// split into that many rows.
+    pub wall_subdivisions: u32,
+    pub triangulator: Triangulator,
+    pub cap_topology: CapTopology,
+    pub cap_fill: CapFill,
+    pub normalize: NormalizeMode,
+    /// Multiplies the x and y coordinates of the vertices. The default of 1.0 makes the image span 1.0,
+    /// so setting it to 50.0 makes the image 50 units wide, e.g. 50 mm for 3D printing.
+    /// The thickness isn't scaled, so it's given in the same units as the scaled image.
+    /// Bevel widths are in image space and are scaled along with it.
+    pub world_scale: f64,
+    /// Keeps the aspect ratio of the image, so the longest side of the image spans 1.0. Otherwise both sides span 1.0.
+    /// Pixel coordinates always keep the aspect ratio.
+    pub preserve_aspect: bool,
+    pub coordinate_space: CoordinateSpace,
+    /// Creates a separate island in the mesh for every shape in the image, instead of only for the first one.
+    pub all_shapes: bool,
+    /// Reverses the winding of every triangle and negates the normals, for importers expecting clockwise triangles.
+    pub flip_winding: bool,
+    /// The materials written by `create_and_save_mesh_from_image`.
+    pub materials: MaterialParams,
+    /// The files written by `create_and_save_mesh_from_image` besides the OBJ and MTL files.
+    pub export: ExportOptions,
+    /// Gives every vertex the color of the image at its contour point, see `Mesh::colors`.
+    /// Only `create_mesh_from_image` has colors to sample, `create_mesh_from_mask` leaves the colors empty.
+    pub vertex_colors: bool,
+    /// The colors of the image are premultiplied by its alpha, so they are divided by the alpha before they're used
+    /// for the vertex colors or the texture written by `create_and_save_mesh_from_image`. The shape is traced
+    /// the same way either way.
+    pub premultiplied_alpha: bool,
+    /// Finds the strong edges in the image inside the shape, see `edges::find_internal_edges`, and adds them to the
+    /// mesh as lines on the front cap, after all triangles. OBJ files write them as `l` elements, which most
+    /// editors import as loose edges to crease or cut seams along. Edges are cut where they leave the shapes in the
+    /// mesh. Only `create_mesh_from_image` has an image to find edges in, `create_mesh_from_mask` adds no lines.
+    pub detect_internal_edges: bool,
+    /// The expected size of the mesh, so its vertex, uv, normal and triangle lists are allocated once up front
+    /// instead of growing as the shapes are added. `None` lets them grow.
+    pub size_hint: Option<SizeHint>,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            contour_params: contour::Params::default(),
+            thickness: ThicknessMode::default(),
+            extrude_mode: ExtrudeMode::default(),
+            style: MeshStyle::default(),
+            include_uvs: true,
+            include_normals: true,
+            uv_mode: UvMode::default(),
+            texture_bleed: 0,
+            mirror_back_uvs: false,
+            weld_seams: false,
+            close_back: true,
+            bevel: None,
+            wall_subdivisions: 0,
+            triangulator: Triangulator::default(),
+            cap_topology: CapTopology::default(),
+            cap_fill: CapFill::default(),
+            normalize: NormalizeMode::default(),
+            world_scale: 1.0,
+            preserve_aspect: false,
+            coordinate_space: CoordinateSpace::default(),
+            all_shapes: false,
+            flip_winding: false,
+            materials: MaterialParams::default(),
+            export: ExportOptions::default(),
+            vertex_colors: false,
+            premultiplied_alpha: false,
+            detect_internal_edges: false,
+            size_hint: None,
+        }
+    }
+}
+
+impl Params {
+    /// Returns a builder starting from the default parameters.
+    pub fn builder() -> ParamsBuilder {
+        ParamsBuilder::default()
+    }
+
+    /// Parameters for 3D printing: a closed, welded mesh 3 mm thick, with the image spanning 50 mm, see
+    /// `world_scale`. Uvs and normals are left out, as slicers don't use them. The contour is simplified more
+    /// than by default, as printers can't reproduce very fine detail anyway, and specks under 0.1% of the image
+    /// area are dropped.
+    pub fn print_quality() -> Params {
+        Params::builder()
+            .simplify_angle(std::f32::consts::PI / 15.0)
+            .min_area(0.001)
+            .world_scale(50.0)
+            .thickness(3.0)
+            .include_uvs(false)
+            .include_normals(false)
+            .weld_seams(true)
+            .close_back(true)
+            .build()
+    }
+
+    /// Parameters for sprites in games: few triangles, with uvs and normals for texturing and lighting. The contour
+    /// is simplified to a few points per curve and triangulated with earcut, and specks under 0.1% of the image
+    /// area are dropped.
+    pub fn game_asset() -> Params {
+        Params::builder()
+            .simplify_angle(std::f32::consts::PI / 12.0)
+            .min_area(0.001)
+            .triangulator(Triangulator::Earcut)
+            .include_uvs(true)
+            .include_normals(true)
+            .build()
+    }
+
+    /// Parameters that follow the shape closely, for renders and close-ups: a dense contour smoothed without
+    /// shrinking the shape, with rounded, smoothly shaded edges.
+    pub fn high_fidelity() -> Params {
+        Params::builder()
+            .smooth_iterations(20)
+            .smooth_mode(contour::SmoothMode::Taubin { lambda: 0.5, mu: -0.53 })
+            .simplify_angle(std::f32::consts::PI / 90.0)
+            .bevel(BevelParams { width: 0.01, segments: 4 })
+            .weld_seams(true)
+            .build()
+    }
+}
+
+/// Builder for `Params`, starting from `Params::default()`.
+#[derive(Default)]
+pub struct ParamsBuilder {
+    params: Params,
+}
+
+impl ParamsBuilder {
+    pub fn contour_params(mut self, contour_params: contour::Params) -> Self {
+        self.params.contour_params = contour_params;
+        self
+    }
+
+    /// Sets a uniform thickness.
+    pub fn thickness(mut self, thickness: f64) -> Self {
+        self.params.thickness = ThicknessMode::Uniform(thickness);
+        self
+    }
+
+    pub fn thickness_mode(mut self, thickness: ThicknessMode) -> Self {
+        self.params.thickness = thickness;
+        self
+    }
+
+    pub fn extrude_mode(mut self, extrude_mode: ExtrudeMode) -> Self {
+        self.params.extrude_mode = extrude_mode;
+        self
+    }
+
+    pub fn style(mut self, style: MeshStyle) -> Self {
+        self.params.style = style;
+        self
+    }
+
+    pub fn include_uvs(mut self, include_uvs: bool) -> Self {
+        self.params.include_uvs = include_uvs;
+        self
+    }
+
+    pub fn include_normals(mut self, include_normals: bool) -> Self {
+        self.params.include_normals = include_normals;
+        self
+    }
+
+    pub fn uv_mode(mut self, uv_mode: UvMode) -> Self {
+        self.params.uv_mode = uv_mode;
+        self
+    }
+
+    pub fn texture_bleed(mut self, texture_bleed: u32) -> Self {
+        self.params.texture_bleed = texture_bleed;
+        self
+    }
+
+    pub fn mirror_back_uvs(mut self, mirror_back_uvs: bool) -> Self {
+        self.params.mirror_back_uvs = mirror_back_uvs;
+        self
+    }
+
+    pub fn weld_seams(mut self, weld_seams: bool) -> Self {
+        self.params.weld_seams = weld_seams;
+        self
+    }
+
+    pub fn close_back(mut self, close_back: bool) -> Self {
+        self.params.close_back = close_back;
+        self
+    }
+
+    pub fn bevel(mut self, bevel: BevelParams) -> Self {
+        self.params.bevel = Some(bevel);
+        self
+    }
+
+    pub fn wall_subdivisions(mut self, wall_subdivisions: u32) -> Self {
+        self.params.wall_subdivisions = wall_subdivisions;
+        self
+    }
+
+    pub fn triangulator(mut self, triangulator: Triangulator) -> Self {
+        self.params.triangulator = triangulator;
+        self
+    }
+
+    pub fn cap_topology(mut self, cap_topology: CapTopology) -> Self {
+        self.params.cap_topology = cap_topology;
+        self
+    }
+
+    pub fn cap_fill(mut self, cap_fill: CapFill) -> Self {
+        self.params.cap_fill = cap_fill;
+        self
+    }
+
+    pub fn normalize(mut self, normalize: NormalizeMode) -> Self {
+        self.params.normalize = normalize;
+        self
+    }
+
+    pub fn world_scale(mut self, world_scale: f64) -> Self {
+        self.params.world_scale = world_scale;
+        self
+    }
+
+    pub fn preserve_aspect(mut self, preserve_aspect: bool) -> Self {
+        self.params.preserve_aspect = preserve_aspect;
+        self
+    }
+
+    pub fn coordinate_space(mut self, coordinate_space: CoordinateSpace) -> Self {
+        self.params.coordinate_space = coordinate_space;
+        self
+    }
+
+    pub fn all_shapes(mut self, all_shapes: bool) -> Self {
+        self.params.all_shapes = all_shapes;
+        self
+    }
+
+    pub fn flip_winding(mut self, flip_winding: bool) -> Self {
+        self.params.flip_winding = flip_winding;
+        self
+    }
+
+    pub fn materials(mut self, materials: MaterialParams) -> Self {
+        self.params.materials = materials;
+        self
+    }
+
+    pub fn export(mut self, export: ExportOptions) -> Self {
+        self.params.export = export;
+        self
+    }
+
+    pub fn vertex_colors(mut self, vertex_colors: bool) -> Self {
+        self.params.vertex_colors = vertex_colors;
+        self
+    }
+
+    pub fn premultiplied_alpha(mut self, premultiplied_alpha: bool) -> Self {
+        self.params.premultiplied_alpha = premultiplied_alpha;
+        self
+    }
+
+    pub fn detect_internal_edges(mut self, detect_internal_edges: bool) -> Self {
+        self.params.detect_internal_edges = detect_internal_edges;
+        self
+    }
+
+    pub fn size_hint(mut self, size_hint: SizeHint) -> Self {
+        self.params.size_hint = Some(size_hint);
+        self
+    }
+
+    pub fn border_offset(mut self, border_offset: f32) -> Self {
+        self.params.contour_params.border_offset = border_offset;
+        self
+    }
+
+    pub fn smooth_iterations(mut self, smooth_iterations: u32) -> Self {
+        self.params.contour_params.smooth_iterations = smooth_iterations;
+        self
+    }
+
+    pub fn smooth_mode(mut self, smooth_mode: contour::SmoothMode) -> Self {
+        self.params.contour_params.smooth_mode = smooth_mode;
+        self
+    }
+
+    pub fn simplify_angle(mut self, simplify_angle: f32) -> Self {
+        self.params.contour_params.simplify_mode = contour::SimplifyMode::Angle(simplify_angle);
+        self
+    }
+
+    pub fn simplify_rdp(mut self, epsilon: f32) -> Self {
+        self.params.contour_params.simplify_mode = contour::SimplifyMode::Rdp(epsilon);
+        self
+    }
+
+    pub fn mask_source(mut self, mask_source: contour::MaskSource) -> Self {
+        self.params.contour_params.mask_source = mask_source;
+        self
+    }
+
+    pub fn pad_border(mut self, pad_border: u32) -> Self {
+        self.params.contour_params.pad_border = pad_border;
+        self
+    }
+
+    pub fn sdf_cutoff(mut self, sdf_cutoff: f32) -> Self {
+        self.params.contour_params.sdf_cutoff = sdf_cutoff;
+        self
+    }
+
+    pub fn contour_threshold(mut self, threshold: u8) -> Self {
+        self.params.contour_params.threshold = threshold;
+        self
+    }
+
+    pub fn contour_algorithm(mut self, algorithm: contour::ContourAlgorithm) -> Self {
+        self.params.contour_params.algorithm = algorithm;
+        self
+    }
+
+    pub fn max_trace_steps(mut self, max_trace_steps: u32) -> Self {
+        self.params.contour_params.max_trace_steps = Some(max_trace_steps);
+        self
+    }
+
+    pub fn close_unclosed_contours(mut self, close_unclosed_contours: bool) -> Self {
+        self.params.contour_params.close_unclosed_contours = close_unclosed_contours;
+        self
+    }
+
+    pub fn close_tolerance(mut self, close_tolerance: f32) -> Self {
+        self.params.contour_params.close_tolerance = close_tolerance;
+        self
+    }
+
+    pub fn min_area(mut self, min_area: f32) -> Self {
+        self.params.contour_params.min_area = min_area;
+        self
+    }
+
+    pub fn mask_offset(mut self, mask_offset: f32) -> Self {
+        self.params.contour_params.mask_offset = mask_offset;
+        self
+    }
+
+    pub fn adaptive_threshold(mut self, adaptive_threshold: bool) -> Self {
+        self.params.contour_params.adaptive_threshold = adaptive_threshold;
+        self
+    }
+
+    pub fn resample_spacing(mut self, resample_spacing: f32) -> Self {
+        self.params.contour_params.resample_spacing = Some(resample_spacing);
+        self
+    }
+
+    pub fn build(self) -> Params {
+        self.params
+    }
+}
+
+/// The materials of the mesh. The front is always textured with the image, the back and the sides can be given a
+/// color instead. The default only writes the diffuse texture or color of each material.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MaterialParams {
+    /// The diffuse color of the back, or `None` to texture it with the image like the front.
+    pub back_color: Option<[f32; 3]>,
+    /// The diffuse color of the sides, or `None` to texture them with the image like the front.
+    pub side_color: Option<[f32; 3]>,
+    /// The path of the texture, the `map_Kd` diffuse map, relative to the OBJ file, e.g. `textures/sprite.png`.
+    /// `None` uses the name of the OBJ file with a `.png` extension.
+    pub texture_path: Option<String>,
+    /// The ambient color `Ka` of every material, or `None` to leave it to the renderer.
+    pub ambient: Option<[f32; 3]>,
+    /// The specular color `Ks` of every material, or `None` to leave it to the renderer.
+    pub specular: Option<[f32; 3]>,
+    /// The specular exponent `Ns` of every material, usually from 0 to 1000, or `None` to leave it to the renderer.
+    pub shininess: Option<f32>,
+}
+
+/// How `create_and_save_mesh_from_image` writes the OBJ file, and the files it writes besides the OBJ and MTL files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// Saves the image as the texture the MTL file refers to, see `MaterialParams::texture_path`.
+    /// With `UvMode::BoundingBox`, only the part of the image covered by the uvs is saved.
+    pub write_texture: bool,
+    /// The number of decimals of the vertex, uv and normal coordinates in the OBJ file. The vertices span
+    /// about [-0.5, 0.5] unless `Params::world_scale` is set, so 5 or 6 decimals are plenty. Defaults to 6,
+    /// for every function writing OBJ files, while earlier versions wrote the full precision of the coordinates.
+    pub decimals: usize,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            write_texture: false,
+            decimals: 6,
+        }
+    }
+}
+
+/// The expected size of a mesh, see `Params::size_hint`. When converting many images of similar size, the number
+/// of points of an earlier one is a good guess for the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SizeHint {
+    /// The number of contour points of all shapes and holes together, after simplification.
+    pub expected_points: usize,
+}
+
+/// Parameters for rounding the edges of the mesh.
+///
+/// The side wall is built from `segments` intermediate rings of the contour. The caps are inset by `width`,
+/// and the rings bulge out to the original contour halfway between the front and the back, following a sine profile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BevelParams {
+    /// How far the caps are inset from the contour, in the [0, 1] image space of the contour,
+    /// or in pixels with `CoordinateSpace::Pixels`.
+    pub width: f32,
+    pub segments: u32,
+}
+
+/// How the caps are triangulated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Triangulator {
+    /// Ear clipping with rgeometry. It doesn't support holes, so shapes with holes are triangulated with earcut.
+    #[default]
+    Earclip,
+    /// Earcut for every shape, which is faster on large contours and more forgiving of nearly self-touching ones.
+    Earcut,
+}
+
+/// Which faces the caps are made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CapTopology {
+    /// The triangles of `Params::triangulator`.
+    #[default]
+    Triangles,
+    /// Neighbouring triangles that together form a convex quad are joined into quads, which subdivide better
+    /// than triangles. The quads of the side walls are kept as well. Only OBJ files keep the quads, see
+    /// `Mesh::quads`, and `Mesh::decimate` turns them back into triangles.
+    QuadDominant,
+}
+
+/// Which points the caps are triangulated through.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CapFill {
+    /// Only the points of the contour, triangulated by `Params::triangulator`. Gives the fewest triangles, but
+    /// long thin ones that span the whole cap.
+    #[default]
+    Contour,
+    /// Also the points of a grid with `cell` spacing inside the contour, in the same units as `BevelParams::width`,
+    /// giving evenly sized triangles. The caps then have vertices inside them, so a `ThicknessMode::Heightmap` shows
+    /// its relief across the caps instead of only along the contour.
+    Grid { cell: f32 },
+}
+
+/// Which part of the texture the uvs cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UvMode {
+    /// The [0, 1] uv square is the whole image.
+    #[default]
+    FullImage,
+    /// The [0, 1] uv square is the bounding box of the contours, rounded out to whole pixels and grown by
+    /// `Params::texture_bleed`, for textures cropped to the shape. `ExportOptions::write_texture` saves the
+    /// image cropped to this box.
+    BoundingBox,
+}
+
+/// How the finished mesh is positioned and scaled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormalizeMode {
+    /// The vertices are left as generated.
+    #[default]
+    None,
+    /// The mesh is translated so the center of its bounding box is at the origin.
+    CenterOnly,
+    /// The mesh is centered like `CenterOnly`, and uniformly scaled so the largest dimension of its bounding box is 1.0.
+    FitUnitCube,
+}
+
+/// The units and orientation of the x and y coordinates of the vertices, before `Params::world_scale`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoordinateSpace {
+    /// The image spans 1.0 and is centered on (0, 0), turned half a turn so it reads correctly from the front,
+    /// where +y is up.
+    #[default]
+    Normalized,
+    /// The vertices are at the pixel coordinates of the contour, with (0, 0) at the top left corner of the image
+    /// and +y down, for aligning the mesh with other data in pixels. The thickness and bevel widths are in pixels too.
+    Pixels,
+}
+
+/// Where the mesh lies along z relative to the image plane at z = 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExtrudeMode {
+    /// The front is at z = 0 and the back at z = thickness.
+    #[default]
+    Forward,
+    /// The front is at z = -thickness / 2 and the back at z = thickness / 2.
+    /// `MeshStyle::FrontPlaneOnly` ignores the thickness, so its plane stays at z = 0.
+    Centered,
+}
+
+/// Which parts of the mesh are generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MeshStyle {
+    /// The front and back caps joined by the side walls.
+    #[default]
+    Solid,
+    /// Only the front cap, as a flat single-sided plane facing -z. Thickness and bevel are ignored.
+    FrontPlaneOnly,
+    /// The caps and side walls, like `Solid`. The mesh is a closed surface without any volume inside, so the two are the same.
+    Shell,
+}
+
+/// How far the back of the mesh is extruded from the front.
+#[derive(Debug, Clone)]
+pub enum ThicknessMode {
+    /// The back is a flat cap at the given distance from the front.
+    Uniform(f64),
+    /// The back is displaced per vertex by sampling the luminance of `image` at the vertex UV coordinate.
+    /// Black pixels give a thickness of `scale` and white pixels a thickness of zero.
+    Heightmap { image: DynamicImage, scale: f64 },
+}
+
+impl Default for ThicknessMode {
+    fn default() -> Self {
+        ThicknessMode::Uniform(0.05)
+    }
+}
+
+impl ThicknessMode {
+    /// Returns the thickness at a contour point, given in the [0, 1] image space of the contour.
+    fn thickness_at(&self, p: [f32; 2]) -> f64 {
+        match self {
+            ThicknessMode::Uniform(thickness) => *thickness,
+            ThicknessMode::Heightmap { image, scale } => {
+                let (width, height) = image.dimensions();
+                if width == 0 || height == 0 {
+                    return 0.0;
+                }
+
+                let x = ((p[0] * width as f32) as u32).min(width - 1);
+                let y = ((p[1] * height as f32) as u32).min(height - 1);
+                let luminance = image.get_pixel(x, y).to_luma()[0] as f64 / 255.0;
+
+                scale * (1.0 - luminance)
+            }
+        }
+    }
+}
+
+/// Looks up the colors of contour points in the image the contour was traced in.
+struct ColorSampler<'a> {
+    image: &'a DynamicImage,
+    /// The coverage of the shape, 255 being fully part of it.
+    mask: &'a GrayImage,
+    /// How far from a contour point, in pixels, the nearest pixel of the shape is searched for.
+    radius: i64,
+    /// Whether the colors of the image are premultiplied by its alpha, see `Params::premultiplied_alpha`.
+    premultiplied_alpha: bool,
+}
+
+impl ColorSampler<'_> {
+    /// Returns the color of the pixel of the shape nearest to `p`, given in the [0, 1] image space of the contour.
+    ///
+    /// `border_offset` grows the contour beyond the shape, so the pixel right under a contour point is usually
+    /// transparent. If no pixel of the shape is within `radius`, the pixel under the point is used after all.
+    fn color_at(&self, p: [f32; 2]) -> [u8; 3] {
+        let (width, height) = (self.image.width() as i64, self.image.height() as i64);
+        let x = (p[0] * width as f32).floor() as i64;
+        let y = (p[1] * height as f32).floor() as i64;
+
+        let mut nearest = None;
+        let mut nearest_distance = i64::MAX;
+        for sy in (y - self.radius).max(0)..(y + self.radius + 1).min(height) {
+            for sx in (x - self.radius).max(0)..(x + self.radius + 1).min(width) {
+                let distance = (sx - x) * (sx - x) + (sy - y) * (sy - y);
+                if distance < nearest_distance && self.mask.get_pixel(sx as u32, sy as u32)[0] >= 128 {
+                    nearest = Some((sx, sy));
+                    nearest_distance = distance;
+                }
+            }
+        }
+
+        let (sx, sy) = nearest.unwrap_or((x.clamp(0, width - 1), y.clamp(0, height - 1)));
+        let mut pixel = self.image.get_pixel(sx as u32, sy as u32);
+        if self.premultiplied_alpha {
+            pixel = unpremultiply(pixel);
+        }
+        let rgb = pixel.to_rgb();
+        [rgb[0], rgb[1], rgb[2]]
+    }
+}
+
+pub fn create_mesh_from_image(img: &DynamicImage, params: Params) -> Result<Mesh, MeshError> {
+    create_mesh_from_image_with_progress(img, params, None)
+}
+
+/// Same as `create_mesh_from_image`, but calls `progress` as the steps of creating the mesh are done.
+pub fn create_mesh_from_image_with_progress(
+    img: &DynamicImage,
+    params: Params,
+    progress: Option<&dyn Fn(Progress)>)
+    -> Result<Mesh, MeshError> {
+
+    let (mesh, _) = mesh_and_uv_bounds_from_image(img, params, progress.unwrap_or(&|_| {}))?;
+    Ok(mesh)
+}
+
+/// Creates the mesh of an image, along with the part of the image that the [0, 1] uv square covers.
+fn mesh_and_uv_bounds_from_image(
+    img: &DynamicImage,
+    params: Params,
+    progress: &dyn Fn(Progress))
+    -> Result<(Mesh, [[f32; 2]; 2]), MeshError> {
+
+    let mask = contour::mask_from_source(img, params.contour_params.mask_source);
+    let outlines = contour::find_outlines_from_coverage(&mask, params.contour_params, params.all_shapes, progress)?;
+
+    let colors = params.vertex_colors.then(|| ColorSampler {
+        image: img,
+        mask: &mask,
+        radius: params.contour_params.border_offset.max(0.0).ceil() as i64 + 1,
+        premultiplied_alpha: params.premultiplied_alpha,
+    });
+
+    let edges = match params.detect_internal_edges {
+        true => edges::internal_edges(img, &mask),
+        false => vec![],
+    };
+
+    mesh_from_outlines(&outlines, &edges, img.dimensions(), params, colors.as_ref(), progress)
+}
+
+/// Same as `create_mesh_from_image`, but the shape is given by a grayscale mask, where pixels above `threshold`
+/// are part of the shape. `params.contour_params.mask_source` is not used.
+pub fn create_mesh_from_mask(mask: &GrayImage, threshold: u8, params: Params) -> Result<Mesh, MeshError> {
+
+    let coverage = contour::binarize(mask, threshold);
+    let outlines = contour::find_outlines_from_coverage(&coverage, params.contour_params, params.all_shapes, &|_| {})?;
+
+    let (mesh, _) = mesh_from_outlines(&outlines, &[], mask.dimensions(), params, None, &|_| {})?;
+    Ok(mesh)
+}
+
+/// Creates the mesh of the outlines found in an image of the given dimensions, with lines along the `edges`
+/// inside them, along with the part of the image that the [0, 1] uv square covers.
+fn mesh_from_outlines(
+    outlines: &[Outline],
+    edges: &[Vec<[f32; 2]>],
+    (width, height): (u32, u32),
+    params: Params,
+    colors: Option<&ColorSampler>,
+    progress: &dyn Fn(Progress))
+    -> Result<(Mesh, [[f32; 2]; 2]), MeshError> {
+
+    let size = match (params.coordinate_space, params.preserve_aspect) {
+        (CoordinateSpace::Pixels, _) => [width as f32, height as f32],
+        (CoordinateSpace::Normalized, true) => {
+            let longest = width.max(height) as f32;
+            [width as f32 / longest, height as f32 / longest]
+        }
+        (CoordinateSpace::Normalized, false) => [1.0, 1.0],
+    };
+    let placement = Placement { space: params.coordinate_space, size };
+
+    // The part of the image that the [0, 1] uv square covers. The bounding box is rounded out to whole pixels,
+    // so that the texture can be cropped to exactly the part the uvs cover.
+    let uv_bounds = match params.uv_mode {
+        UvMode::FullImage => [[0.0, 0.0], [1.0, 1.0]],
+        UvMode::BoundingBox => {
+            let [min, max] = outlines.iter().flat_map(|outline| outline.outer.iter()).fold(
+                [[f32::MAX; 2], [f32::MIN; 2]],
+                |[min, max], p| [[min[0].min(p[0]), min[1].min(p[1])], [max[0].max(p[0]), max[1].max(p[1])]],
+            );
+            let (f_width, f_height) = (width as f32, height as f32);
+            let bleed = params.texture_bleed as f32;
+            [
+                [((min[0] * f_width).floor() - bleed) / f_width, ((min[1] * f_height).floor() - bleed) / f_height],
+                [((max[0] * f_width).ceil() + bleed) / f_width, ((max[1] * f_height).ceil() + bleed) / f_height],
+            ]
+        }
+    };
+
+    progress(Progress::Triangulating);
+    let mut mesh = match params.size_hint {
+        Some(hint) => mesh_with_capacity(hint.expected_points, &params),
+        None => Mesh{vertices: vec![], triangles: vec![], uv_vertices: vec![], normals: vec![], surfaces: vec![], colors: vec![], quads: vec![]},
+    };
+    for outline in outlines {
+        mesh.append(mesh_from_outline(outline, placement, uv_bounds, &params, colors)?);
+    }
+    if !edges.is_empty() {
+        mesh.append(edge_lines(edges, outlines, placement, uv_bounds, &params, colors));
+    }
+
+    let mesh = mesh.normalize(params.normalize);
+
+    let mesh = match params.weld_seams {
+        true => mesh.weld_seams(WELD_EPSILON),
+        false => mesh,
+    };
+
+    let mesh = match params.flip_winding {
+        true => mesh.flip_winding(),
+        false => mesh,
+    };
+
+    progress(Progress::Done);
+    Ok((mesh, uv_bounds))
 }
 
-/// Parameters for creating a mesh, including contour parameters, thickness, and whether to include UVs.
-pub struct Params {
-    pub contour_params: contour::Params,
-    pub thickness: f64,
-    pub include_uvs: bool,
+/// An empty mesh with room for the mesh of outlines with `n_points` points in total, see `SizeHint`.
+/// The points added by `CapFill::Grid` and `Params::detect_internal_edges` aren't counted.
+fn mesh_with_capacity(n_points: usize, params: &Params) -> Mesh {
+    let front_only = params.style == MeshStyle::FrontPlaneOnly;
+    let n_rows = match (front_only, params.bevel) {
+        (true, _) => 0,
+        (false, Some(bevel)) => (bevel.segments as usize + 1) * (params.wall_subdivisions as usize + 1),
+        (false, None) => params.wall_subdivisions as usize + 1,
+    };
+
+    // The caps, the rings in between and the copies of the caps for the side walls, or only the front cap.
+    // A cap has about one triangle per point, and every row of the wall two per point.
+    let n_caps = if front_only { 1 } else { 1 + params.close_back as usize };
+    let n_vertices = match front_only {
+        true => n_points,
+        false => (n_caps + n_rows + 1) * n_points,
+    };
+    let n_normals = match (front_only, params.bevel) {
+        (true, _) => n_points,
+        (false, Some(_)) => (n_caps + n_rows + 1) * n_points,
+        (false, None) => (n_caps + 1) * n_points,
+    };
+    let n_triangles = n_caps * n_points + 2 * n_rows * n_points;
+
+    Mesh{
+        vertices: Vec::with_capacity(n_vertices),
+        triangles: Vec::with_capacity(n_triangles),
+        uv_vertices: Vec::with_capacity(if params.include_uvs { n_vertices } else { 0 }),
+        normals: Vec::with_capacity(if params.include_normals { n_normals } else { 0 }),
+        surfaces: Vec::with_capacity(n_triangles),
+        colors: Vec::with_capacity(if params.vertex_colors { n_vertices } else { 0 }),
+        quads: Vec::with_capacity(if params.cap_topology == CapTopology::QuadDominant { n_triangles } else { 0 }),
+    }
 }
 
-impl Default for Params {
-    fn default() -> Self {
-        Params {
-            contour_params: contour::Params::default(),
-            thickness: 0.05,
-            include_uvs: true,
+/// Places the points of a contour, in the [0, 1] image space, in the world and at the vertices.
+#[derive(Debug, Clone, Copy)]
+struct Placement {
+    space: CoordinateSpace,
+    /// The size of the image in the world.
+    size: [f32; 2],
+}
+
+impl Placement {
+    fn to_world(self, p: [f32; 2]) -> [f32; 2] {
+        match self.space {
+            CoordinateSpace::Normalized => [0.5 + (p[0] - 0.5) * self.size[0], 0.5 + (p[1] - 0.5) * self.size[1]],
+            CoordinateSpace::Pixels => [p[0] * self.size[0], p[1] * self.size[1]],
+        }
+    }
+
+    fn to_image(self, p: [f32; 2]) -> [f32; 2] {
+        match self.space {
+            CoordinateSpace::Normalized => [0.5 + (p[0] - 0.5) / self.size[0], 0.5 + (p[1] - 0.5) / self.size[1]],
+            CoordinateSpace::Pixels => [p[0] / self.size[0], p[1] / self.size[1]],
+        }
+    }
+
+    /// The x and y of the vertex at a world position.
+    fn to_vertex(self, p: [f32; 2]) -> [f64; 2] {
+        match self.space {
+            CoordinateSpace::Normalized => [(0.5 - p[0]) as f64, (0.5 - p[1]) as f64],
+            CoordinateSpace::Pixels => [p[0] as f64, p[1] as f64],
+        }
+    }
+
+    /// Normalized vertices are at `0.5 - p`, which flips both axes of directions in the world.
+    fn direction_sign(self) -> f32 {
+        match self.space {
+            CoordinateSpace::Normalized => -1.0,
+            CoordinateSpace::Pixels => 1.0,
         }
     }
 }
 
-pub fn create_mesh_from_image(img: &DynamicImage, params: Params) -> Result<Mesh, Box<dyn Error>> {
-    
-    let contour = find_contour_from_transparency_with_offset(img, params.contour_params)?;
+/// Creates the mesh of a single shape, with a front and a back cap and side walls along the outer contour and the holes.
+fn mesh_from_outline(
+    outline: &Outline,
+    placement: Placement,
+    uv_bounds: [[f32; 2]; 2],
+    params: &Params,
+    colors: Option<&ColorSampler>)
+    -> Result<Mesh, MeshError> {
+
+    let front_only = params.style == MeshStyle::FrontPlaneOnly;
+    let (thickness, bevel) = (&params.thickness, if front_only { None } else { params.bevel });
+
+    // Coincident points give degenerate triangles, or make the triangulation fail.
+    let mut outline = outline.clone().map(|contour| contour.dedup(DEDUP_EPSILON));
+    outline.holes.retain(|hole| hole.len() >= 3);
+    if outline.outer.len() < 3 {
+        return Err(MeshError::InvalidPolygon);
+    }
+
+    // The triangulators give overlapping or missing triangles for a contour that crosses itself.
+    if outline.iter().any(|c| !c.is_simple()) {
+        return Err(MeshError::SelfIntersectingContour);
+    }
+
+    // The side walls face away from the material only if the outer contour is counter-clockwise and the holes
+    // are clockwise, whichever way they were traced.
+    let outline = &Outline {
+        outer: outline.outer.ensure_ccw(),
+        holes: outline.holes.into_iter().map(Contour::ensure_cw).collect(),
+    };
+
+    // The outer contour and the holes share one list of points, the outer contour coming first.
+    let contour: Vec<[f32; 2]> = outline.iter().flat_map(|c| c.iter().copied()).collect();
+    let mut loops: Vec<Range<usize>> = vec![];
+    for c in outline.iter() {
+        let start = loops.last().map_or(0, |l| l.end);
+        loops.push(start..start + c.len());
+    }
 
     let n_points = contour.len();
-    
-    let polygon = Polygon::new(contour.iter().map(|p| Point::new([p[0], p[1]])).collect()).unwrap();
-    
-    let front_vertices = contour.iter().map(|p| Vertex{x: (0.5 - p[0]) as f64, y: (0.5 - p[1]) as f64, z: 0.0});
-    let back_vertices = contour.iter().map(|p| Vertex{x: (0.5 - p[0]) as f64, y: (0.5 - p[1]) as f64, z: params.thickness});
+
+    // The contour is scaled per axis to [0, 1], so the world positions are stretched back to the size of the image.
+    // UVs and thickness are still looked up in image space.
+    let world: Vec<[f32; 2]> = contour.iter().map(|p| placement.to_world(*p)).collect();
+
+    // The side wall runs through a number of rings of the contour, from the front cap to the back cap.
+    // The caps are the first two rings in the vertex list, so an unbeveled and unsubdivided mesh has no rings
+    // in between. Every row of the wall, one per bevel segment, is split into `wall_subdivisions + 1` rows.
+    let n_rows = bevel.map_or(1, |bevel| bevel.segments as usize + 1) * (params.wall_subdivisions as usize + 1);
+    let n_rings = n_rows + 1;
+    let ring_t = |r: usize| r as f32 / (n_rings - 1) as f32;
+    let ring_start = |r: usize| match r {
+        0 => 0,
+        r if r == n_rings - 1 => n_points,
+        r => (r + 1) * n_points,
+    };
+    let ring_order = [0, n_rings - 1].into_iter().chain(1..n_rings - 1);
+
+    // Material lies to the same side of every loop, as holes wind opposite to the outer contour.
+    let inward_sign = contour::signed_area(&outline.outer).signum();
+    let rings: Vec<Vec<[f32; 2]>> = ring_order.clone().map(|r| match bevel {
+        Some(bevel) => {
+            let inset = bevel.width * (1.0 - (std::f32::consts::PI * ring_t(r)).sin());
+            inset_contour(&world, &loops, inward_sign, inset)
+        }
+        None => world.clone(),
+    }).collect();
+
+    let world_scale = params.world_scale;
+    let thicknesses: Vec<f64> = contour.iter().map(|p| thickness.thickness_at(*p)).collect();
+    let front_t = front_t(params);
+    let mut vertices: Vec<Vertex> = ring_order.clone().zip(&rings).flat_map(|(r, ring)| {
+        let t = front_t + ring_t(r) as f64;
+        ring.iter().zip(&thicknesses).map(move |(p, thickness)| {
+            let [x, y] = placement.to_vertex(*p);
+            Vertex{x: x * world_scale, y: y * world_scale, z: t * thickness}
+        })
+    }).collect();
+
+    // The side walls get their own copies of the cap rings, so the caps and the walls don't share any vertices.
+    vertices.extend_from_within(0..2 * n_points);
+    let side_ring_start = |r: usize| match r {
+        0 => n_rings * n_points,
+        r if r == n_rings - 1 => (n_rings + 1) * n_points,
+        r => ring_start(r),
+    };
    
-    let triangulation: Vec<(usize, usize, usize)> = rgeometry::algorithms::triangulation::earclip::earclip(&polygon).map(|(p0, p1, p2)| (p0.usize(), p1.usize(), p2.usize())).collect();
-    let front_triangles = triangulation.iter()
-    .map(|(v0, v1, v2)| triangle_from_indices(*v0, *v2, *v1));
+    let triangulation = triangulate(outline, params.triangulator)?;
 
-    let back_triangles =  triangulation.iter()
-    .map(|(v0, v1, v2)| triangle_from_indices(*v0+n_points, *v1 + n_points, *v2 + n_points));
+    // The points inside the caps of `CapFill::Grid`. They come after all other vertices, uvs and normals,
+    // first those of the front cap and then those of the back cap.
+    let interior = match params.cap_fill {
+        CapFill::Contour => vec![],
+        CapFill::Grid { cell } => grid_points(&rings[0], &loops, &triangulation, cell),
+    };
+    let n_interior = interior.len();
+    let cap_points: Vec<[f32; 2]> = rings[0].iter().chain(&interior).copied().collect();
+    let mut triangulation = triangulation;
+    insert_interior_points(&mut triangulation, &cap_points, n_points);
 
-    let main_triangles = front_triangles.chain(back_triangles);
-    let vertices = front_vertices.chain(back_vertices);
-    
-    let uvs = match params.include_uvs {
-        true => {
-            let front_uv_vertices = contour.iter().map(|p| TVertex{u: p[0] as f64, v: 1.0- p[1] as f64, w: 0.0});
-            let back_uv_vertices = contour.iter().map(|p| TVertex{u: p[0] as f64, v: 1.0 - p[1] as f64, w: 0.0});
-            front_uv_vertices.chain(back_uv_vertices).collect()
+    let (triangulation, cap_quads) = match params.cap_topology {
+        CapTopology::Triangles => (triangulation, vec![]),
+        CapTopology::QuadDominant => pair_triangles(triangulation, &cap_points),
+    };
+
+    // The side normals come after the `2 * n_points` normals of the caps.
+    // Straight walls share one normal per contour point, beveled walls get one per ring.
+    let side_normal_start = |r: usize| 2 * n_points + if bevel.is_some() { ring_start(r) } else { 0 };
+    let side_normal_rings = match (params.include_normals, bevel) {
+        (false, _) => 0,
+        (true, Some(_)) => n_rings,
+        (true, None) => 1,
+    };
+
+    // The corner at vertex `v` with normal `n`, without the uv or normal if they are left out.
+    // The uvs are in the same order as the vertices.
+    let corner = |v: usize, n: usize| (v, params.include_uvs.then_some(v), params.include_normals.then_some(n));
+
+    // The corner of the front or back cap at point `i` of `cap_points`.
+    let (interior_start, interior_normal_start) = match front_only {
+        true => (n_points, n_points),
+        false => ((n_rings + 2) * n_points, (2 + side_normal_rings) * n_points),
+    };
+    let cap_corner = |i: usize, back: bool| match i < n_points {
+        true => corner(i + back as usize * n_points, i + back as usize * n_points),
+        false => {
+            let k = i - n_points + back as usize * n_interior;
+            corner(interior_start + k, interior_normal_start + k)
         }
+    };
+
+    let front_triangles = triangulation.iter()
+    .map(|[v0, v1, v2]| Primitive::Triangle(cap_corner(*v0, false), cap_corner(*v2, false), cap_corner(*v1, false)));
+
+    let n_back_triangles = if params.close_back { triangulation.len() } else { 0 };
+    let back_triangles =  triangulation.iter().take(n_back_triangles)
+    .map(|[v0, v1, v2]| Primitive::Triangle(cap_corner(*v0, true), cap_corner(*v1, true), cap_corner(*v2, true)));
+
+    let interior_vertices = [0.0, 1.0].into_iter().flat_map(|ring_t| interior.iter().map(move |p| {
+        let thickness = thickness.thickness_at(placement.to_image(*p));
+        let [x, y] = placement.to_vertex(*p);
+        Vertex{x: x * world_scale, y: y * world_scale, z: (front_t + ring_t) * thickness}
+    }));
+
+    let uv = |p: [f32; 2], mirror: bool| uv_at(placement.to_image(p), uv_bounds, mirror);
+    let uvs: Vec<TVertex> = match params.include_uvs {
+        true => rings.iter().chain(&rings[..2]).enumerate().flat_map(|(ring, points)| {
+            // The back cap is the second ring. The side walls use their own copies of the cap rings, which aren't mirrored.
+            let mirror = params.mirror_back_uvs && ring == 1;
+            points.iter().map(move |p| uv(*p, mirror))
+        }).collect(),
         false => vec![]
     };
+    let interior_uvs: Vec<TVertex> = match params.include_uvs {
+        true => interior.iter().map(|p| uv(*p, false)).chain(interior.iter().map(|p| uv(*p, params.mirror_back_uvs))).collect(),
+        false => vec![],
+    };
 
+    let n_main_normals = if params.include_normals { 2 * n_points } else { 0 };
     let main_normals = 
     contour.iter().map(|_| Vertex{x: 0.0, y: 0.0, z: -1.0})
-    .chain(contour.iter().map(|_| Vertex{x: 0.0, y: 0.0, z: 1.0}));
+    .chain(contour.iter().map(|_| Vertex{x: 0.0, y: 0.0, z: 1.0}))
+    .take(n_main_normals);
+    let n_interior_normals = if params.include_normals { 2 * n_interior } else { 0 };
+    let interior_normals = 
+    interior.iter().map(|_| Vertex{x: 0.0, y: 0.0, z: -1.0})
+    .chain(interior.iter().map(|_| Vertex{x: 0.0, y: 0.0, z: 1.0}))
+    .take(n_interior_normals);
+
+    // Every ring, including the copies for the side walls, has the colors of the contour points.
+    let point_colors: Vec<[u8; 3]> = colors.map_or(vec![], |colors| contour.iter().map(|p| colors.color_at(*p)).collect());
+    let mut vertex_colors: Vec<[u8; 3]> = point_colors.iter().copied().cycle().take(point_colors.len() * (n_rings + 2)).collect();
+    let interior_colors: Vec<[u8; 3]> = colors.map_or(vec![], |colors| interior.iter().map(|p| colors.color_at(placement.to_image(*p))).collect());
 
-    let mut side_triangles:Vec<Primitive> = vec![];
-    let mut side_normals:Vec<Vertex> = vec![];
+    // The front cap comes first in the vertex, uv and normal lists, so it's enough to cut the lists short.
+    if front_only {
+        vertices.truncate(n_points);
+        vertices.extend(interior_vertices.take(n_interior));
+        vertex_colors.truncate(point_colors.len());
+        vertex_colors.extend(&interior_colors);
+        return Ok(Mesh{
+            vertices,
+            triangles: front_triangles.collect(),
+            uv_vertices: uvs.into_iter().take(n_points).chain(interior_uvs.into_iter().take(n_interior)).collect(),
+            normals: main_normals.take(n_points).chain(interior_normals.take(n_interior)).collect(),
+            surfaces: vec![Surface::Front; triangulation.len()],
+            colors: vertex_colors,
+            quads: cap_quads,
+        });
+    }
+
+    let main_triangles = front_triangles.chain(back_triangles);
+
+    let mut side_triangles:Vec<Primitive> = Vec::with_capacity(2 * (n_rings - 1) * n_points);
+    let mut side_normals:Vec<Vertex> = Vec::with_capacity(side_normal_rings * n_points);
+
+    // Holes wind in the opposite direction of the outer contour, so their walls face into the hole.
+    for range in loops.iter().cloned() {
+        let (first, last) = (range.start, range.end - 1);
+
+        for i in range {
+            let next = if i == last {first} else {i + 1};
+
+            for r in 0..n_rings - 1 {
+                let (a, b) = (side_ring_start(r), side_ring_start(r + 1));
+                let (na, nb) = (side_normal_start(r), side_normal_start(r + 1));
+
+                side_triangles.push(Primitive::Triangle(
+                    corner(a + i, na + i),
+                    corner(b + next, nb + next),
+                    corner(b + i, nb + i)));
+                side_triangles.push(Primitive::Triangle(
+                    corner(a + i, na + i),
+                    corner(a + next, na + next),
+                    corner(b + next, nb + next)));
+            }
+        }
+    }
+
+    for r in ring_order.take(side_normal_rings) {
+        for range in loops.iter().cloned() {
+            let (first, last) = (range.start, range.end - 1);
+
+            for i in range {
+                let prev = if i == first {last} else {i - 1};
+                let next = if i == last {first} else {i + 1};
+
+                let v0 = world[prev];
+                let v1 = world[i];
+                let v2 = world[next];
+
+                let direction_0 = normal_of_line(v0, v1);
+                let direction_1 = normal_of_line(v1, v2);
+                let direction = [(direction_0[0] + direction_1[0]) / 2.0, (direction_0[1] + direction_1[1]) / 2.0];
+
+                // Perpendicular to the averaged direction of the two edges, away from the material.
+                let outward = normalize_2d([inward_sign * direction[1], -inward_sign * direction[0]]);
+                let outward = outward.map(|c| c * placement.direction_sign());
+
+                let Some(bevel) = bevel else {
+                    side_normals.push(Vertex{x: outward[0] as f64, y: outward[1] as f64, z: 0.0});
+                    continue;
+                };
 
-    for i in 0..n_points {
-        let prev = if i == 0 {n_points - 1} else {i - 1};
-        let next = (i + 1) % n_points;
+                // The wall profile moves outward by `width * world_scale * PI * cos(PI * t)` per unit of thickness,
+                // so the normal tilts towards the front near the front cap and towards the back near the back cap.
+                let thickness = thicknesses[i] as f32;
+                let slope = bevel.width * world_scale as f32 * std::f32::consts::PI * (std::f32::consts::PI * ring_t(r)).cos();
+                let len = (thickness * thickness + slope * slope).sqrt().max(f32::EPSILON);
+                side_normals.push(Vertex{
+                    x: (outward[0] * thickness / len) as f64,
+                    y: (outward[1] * thickness / len) as f64,
+                    z: (-slope / len) as f64,
+                });
+            }
+        }
+    }
 
-        side_triangles.push(Primitive::Triangle(
-            (i, Some(i), Some(i+2*n_points)), 
-            (next+n_points, Some(next+n_points), Some(next+2*n_points)), 
-            (i + n_points, Some(i+n_points), Some(i + 2*n_points))));
-        side_triangles.push(Primitive::Triangle(
-            (i, Some(i), Some(i + 2*n_points)), 
-            (next, Some(next), Some(next + 2*n_points)), 
-            (next + n_points, Some(next+n_points), Some(next + 2*n_points))));
-    
-        let v0 = contour[prev];
-        let v1 = contour[i];
-        let v2 = contour[next];
+    let surfaces = std::iter::repeat_n(Surface::Front, triangulation.len())
+        .chain(std::iter::repeat_n(Surface::Back, n_back_triangles))
+        .chain(std::iter::repeat_n(Surface::Side, side_triangles.len()))
+        .collect();
 
-        let normal_0 = normal_of_line(v0, v1);
-        let normal_1 = normal_of_line(v1, v2);
+    // Every quad of the side walls was added as two triangles in a row.
+    let quads = match params.cap_topology {
+        CapTopology::Triangles => vec![],
+        CapTopology::QuadDominant => cap_quads.iter().chain(cap_quads.iter().take(n_back_triangles)).copied()
+            .chain((0..side_triangles.len()).map(|i| i % 2 == 0))
+            .collect(),
+    };
 
-        let normal = [(normal_0[0] + normal_1[0]) / 2.0, (normal_0[1] + normal_1[1]) / 2.0];
-        side_normals.push(Vertex{x: normal[0] as f64, y: normal[1] as f64, z: 0.0});
+    vertices.extend(interior_vertices);
+    if !vertex_colors.is_empty() {
+        vertex_colors.extend(interior_colors.iter().chain(&interior_colors));
     }
 
     let mesh = Mesh{
-        vertices: vertices.collect(),
+        vertices,
         triangles: main_triangles.chain(side_triangles).collect(),
-        uv_vertices: uvs.clone(),
-        normals: main_normals.into_iter().chain(side_normals).collect(),
+        uv_vertices: uvs.into_iter().chain(interior_uvs).collect(),
+        normals: main_normals.into_iter().chain(side_normals).chain(interior_normals).collect(),
+        surfaces,
+        colors: vertex_colors,
+        quads,
     };
 
-    Ok(mesh)
+    // The side walls have their own copies of the back cap vertices, so an open back leaves the vertices,
+    // uvs and normals of the back cap unused.
+    Ok(match params.close_back {
+        true => mesh,
+        false => decimate::remove_unused(mesh),
+    })
+}
+
+/// Where the front cap lies along z, as a fraction of the thickness.
+fn front_t(params: &Params) -> f64 {
+    match (params.extrude_mode, params.style) {
+        (ExtrudeMode::Centered, MeshStyle::Solid | MeshStyle::Shell) => -0.5,
+        _ => 0.0,
+    }
+}
+
+/// The uv of a point in the [0, 1] image space, mirrored horizontally if `mirror` is set.
+fn uv_at(p: [f32; 2], [min, max]: [[f32; 2]; 2], mirror: bool) -> TVertex {
+    let p = [(p[0] - min[0]) / (max[0] - min[0]).max(f32::EPSILON), (p[1] - min[1]) / (max[1] - min[1]).max(f32::EPSILON)];
+    let u = if mirror { 1.0 - p[0] } else { p[0] };
+    TVertex{u: u as f64, v: 1.0 - p[1] as f64, w: 0.0}
+}
+
+/// The lines along the parts of `edges` inside `outlines`, on the front cap, for `Params::detect_internal_edges`.
+/// The lines have uvs if the caps do, but no normals.
+fn edge_lines(
+    edges: &[Vec<[f32; 2]>],
+    outlines: &[Outline],
+    placement: Placement,
+    uv_bounds: [[f32; 2]; 2],
+    params: &Params,
+    colors: Option<&ColorSampler>)
+    -> Mesh {
+
+    let inside = |p: [f32; 2]| outlines.iter().any(|outline| {
+        contour::contains_point(&outline.outer, p) && !outline.holes.iter().any(|hole| contour::contains_point(hole, p))
+    });
+
+    let mut mesh = Mesh{vertices: vec![], triangles: vec![], uv_vertices: vec![], normals: vec![], surfaces: vec![], colors: vec![], quads: vec![]};
+    for edge in edges {
+        let mut previous: Option<usize> = None;
+        for p in edge {
+            if !inside(*p) {
+                previous = None;
+                continue;
+            }
+
+            let [x, y] = placement.to_vertex(placement.to_world(*p));
+            let z = front_t(params) * params.thickness.thickness_at(*p);
+            mesh.vertices.push(Vertex{x: x * params.world_scale, y: y * params.world_scale, z});
+            if params.include_uvs {
+                mesh.uv_vertices.push(uv_at(*p, uv_bounds, false));
+            }
+            if let Some(colors) = colors {
+                mesh.colors.push(colors.color_at(*p));
+            }
+
+            let v = mesh.vertices.len() - 1;
+            if let Some(previous) = previous {
+                mesh.triangles.push(Primitive::Line((previous, params.include_uvs.then_some(previous), None), (v, params.include_uvs.then_some(v), None)));
+            }
+            previous = Some(v);
+        }
+    }
+    mesh.surfaces = vec![Surface::Front; mesh.triangles.len()];
+
+    // A point whose neighbours both lie outside the shapes is left without a line.
+    decimate::remove_unused(mesh)
+}
+
+/// Joins neighbouring triangles into convex quads, for `CapTopology::QuadDominant`.
+///
+/// Returns the triangles reordered so the two triangles of every quad come in a row, and whether each triangle
+/// forms a quad with the one after it. The longest shared edges are removed first, as they give the squarest quads.
+fn pair_triangles(triangles: Vec<[usize; 3]>, points: &[[f32; 2]]) -> (Vec<[usize; 3]>, Vec<bool>) {
+    // The triangles all wind the same way, so the neighbour across an edge has the same edge reversed.
+    let edges: HashMap<(usize, usize), usize> = triangles.iter().enumerate()
+        .flat_map(|(t, [v0, v1, v2])| [((*v0, *v1), t), ((*v1, *v2), t), ((*v2, *v0), t)])
+        .collect();
+
+    let mut candidates: Vec<(f32, usize, usize)> = vec![];
+    for (t, triangle) in triangles.iter().enumerate() {
+        for i in 0..3 {
+            let [u, x, y] = [triangle[i], triangle[(i + 1) % 3], triangle[(i + 2) % 3]];
+            let Some(&other) = edges.get(&(y, x)) else { continue };
+            let w = triangles[other].into_iter().find(|v| *v != x && *v != y).unwrap_or(u);
+            if t < other && is_convex([u, x, w, y].map(|v| points[v])) {
+                candidates.push((distance_2d(points[x], points[y]), t, other));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+    let mut partner: Vec<Option<usize>> = vec![None; triangles.len()];
+    for (_, t, other) in candidates {
+        if partner[t].is_none() && partner[other].is_none() {
+            partner[t] = Some(other);
+            partner[other] = Some(t);
+        }
+    }
+
+    let (mut paired, mut quads) = (Vec::with_capacity(triangles.len()), Vec::with_capacity(triangles.len()));
+    for (t, triangle) in triangles.iter().enumerate() {
+        match partner[t] {
+            Some(other) if other < t => {}
+            Some(other) => {
+                paired.extend([*triangle, triangles[other]]);
+                quads.extend([true, false]);
+            }
+            None => {
+                paired.push(*triangle);
+                quads.push(false);
+            }
+        }
+    }
+    (paired, quads)
+}
+
+/// Whether the quad turns the same way at every corner, with no three corners in a line.
+fn is_convex(quad: [[f32; 2]; 4]) -> bool {
+    let turns = (0..4).map(|i| {
+        let [a, b, c] = [quad[i], quad[(i + 1) % 4], quad[(i + 2) % 4]];
+        (b[0] - a[0]) * (c[1] - b[1]) - (b[1] - a[1]) * (c[0] - b[0])
+    });
+    let signs: Vec<f32> = turns.map(|turn| if turn == 0.0 { 0.0 } else { turn.signum() }).collect();
+    signs[0] != 0.0 && signs.iter().all(|sign| *sign == signs[0])
+}
+
+/// Triangulates the area inside the outer contour and outside the holes.
+///
+/// The returned indices refer to the points of the outline in the order of `Outline::iter`,
+/// and every triangle is counter-clockwise.
+fn triangulate(outline: &Outline, triangulator: Triangulator) -> Result<Vec<[usize; 3]>, MeshError> {
+    let n_points: usize = outline.iter().map(|contour| contour.len()).sum();
+
+    // Points in a straight line between their neighbours, as `Contour::resample` places along straight edges, give
+    // triangles without area with earclip, are left out by earcut, or give slivers that face the wrong way from
+    // rounding. They are triangulated without, and added back to the triangles along their edges afterwards.
+    let kept_per_contour: Vec<Vec<bool>> = outline.iter().map(not_between_neighbours).collect();
+    let reduce = |contour: &Contour, kept: &[bool]| -> Contour {
+        contour.iter().zip(kept).filter(|(_, kept)| **kept).map(|(p, _)| *p).collect()
+    };
+    let reduced = Outline {
+        outer: reduce(&outline.outer, &kept_per_contour[0]),
+        holes: outline.holes.iter().zip(&kept_per_contour[1..]).map(|(hole, kept)| reduce(hole, kept)).collect(),
+    };
+    let kept = kept_per_contour.concat();
+    let kept_indices: Vec<usize> = (0..n_points).filter(|i| kept[*i]).collect();
+
+    let triangles: Vec<[usize; 3]> = match triangulator {
+        Triangulator::Earclip if reduced.holes.is_empty() => {
+            let polygon = Polygon::new(reduced.outer.iter().map(|p| Point::new([p[0], p[1]])).collect())
+                .map_err(|_| MeshError::InvalidPolygon)?;
+            rgeometry::algorithms::triangulation::earclip::earclip(&polygon).map(|(p0, p1, p2)| [p0.usize(), p1.usize(), p2.usize()]).collect()
+        }
+        // rgeometry's earclip doesn't support holes, so polygons with holes are triangulated with earcut.
+        Triangulator::Earclip | Triangulator::Earcut => earcut(&reduced, n_points)?,
+    };
+    let mut triangles = triangles.into_iter().map(|triangle| triangle.map(|i| kept_indices[i])).collect();
+    let mut start = 0;
+    for contour in outline.iter() {
+        insert_left_out_points(&mut triangles, start..start + contour.len(), &kept);
+        start += contour.len();
+    }
+
+    // A polygon with holes is covered by two triangles per hole more than a polygon with the same number of points
+    // without holes. Fewer triangles than that leave part of the cap open.
+    if triangles.len() + 2 < n_points + 2 * outline.holes.len() {
+        return Err(MeshError::TriangulationFailed { points: n_points });
+    }
+
+    Ok(triangles)
+}
+
+/// Whether each point of the contour is kept for triangulation, which it isn't if it lies in a straight line
+/// between its neighbours, within `STRAIGHT_EPSILON`. All points are kept if fewer than three would be.
+fn not_between_neighbours(contour: &Contour) -> Vec<bool> {
+    let n_points = contour.len();
+    let kept: Vec<bool> = (0..n_points).map(|i| {
+        let [a, b, c] = [contour[(i + n_points - 1) % n_points], contour[i], contour[(i + 1) % n_points]];
+        let (ab, bc) = ([b[0] - a[0], b[1] - a[1]], [c[0] - b[0], c[1] - b[1]]);
+        let sin = (ab[0] * bc[1] - ab[1] * bc[0]) / (distance_2d(a, b) * distance_2d(b, c)).max(f32::MIN_POSITIVE);
+        sin.abs() > STRAIGHT_EPSILON || ab[0] * bc[0] + ab[1] * bc[1] <= 0.0
+    }).collect();
+
+    match kept.iter().filter(|kept| **kept).count() {
+        n if n < 3 => vec![true; n_points],
+        _ => kept,
+    }
+}
+
+/// Adds the points of the loop that weren't kept for triangulation to the triangles. The points between two kept
+/// points lie on the edge between them, so the triangle with that edge is split into a fan through them.
+fn insert_left_out_points(triangles: &mut Vec<[usize; 3]>, points: Range<usize>, kept: &[bool]) {
+    let Some(first_kept) = points.clone().find(|i| kept[*i]) else { return };
+    let n_points = points.len();
+    let next = |i: usize| points.start + (i - points.start + 1) % n_points;
+
+    // Triangles are counter-clockwise, so they run along the outer contour and the clockwise holes in loop order.
+    let mut edges: HashMap<(usize, usize), usize> = triangles.iter().enumerate()
+        .flat_map(|(t, [v0, v1, v2])| [((*v0, *v1), t), ((*v1, *v2), t), ((*v2, *v0), t)])
+        .collect();
+
+    let mut p = first_kept;
+    loop {
+        let mut q = next(p);
+        let mut left_out = vec![];
+        while !kept[q] {
+            left_out.push(q);
+            q = next(q);
+        }
+
+        if let (false, Some(&t)) = (left_out.is_empty(), edges.get(&(p, q))) {
+            let triangle = triangles[t];
+            let x = triangle.into_iter().find(|v| *v != p && *v != q).unwrap_or(p);
+            let fan: Vec<usize> = std::iter::once(p).chain(left_out).chain(std::iter::once(q)).collect();
+            for (k, pair) in fan.windows(2).enumerate() {
+                // The first triangle of the fan takes the place of the one it splits.
+                let new_triangle = [pair[0], pair[1], x];
+                let index = if k == 0 { t } else { triangles.push(new_triangle); triangles.len() - 1 };
+                triangles[index] = new_triangle;
+                for edge in [(pair[0], pair[1]), (pair[1], x), (x, pair[0])] {
+                    edges.insert(edge, index);
+                }
+            }
+        }
+
+        p = q;
+        if p == first_kept {
+            break;
+        }
+    }
+}
+
+/// The points of a grid with `cell` spacing that lie in one of the cap `triangles`, for `CapFill::Grid`. Points
+/// closer than half a cell to the contour are left out, as they would give slivers against its edges.
+fn grid_points(ring: &[[f32; 2]], loops: &[Range<usize>], triangles: &[[usize; 3]], cell: f32) -> Vec<[f32; 2]> {
+    if !cell.is_finite() || cell <= 0.0 {
+        return vec![];
+    }
+
+    let outer = &ring[loops[0].clone()];
+    let (mut min, mut max) = ([f32::MAX; 2], [f32::MIN; 2]);
+    for p in outer {
+        min = [min[0].min(p[0]), min[1].min(p[1])];
+        max = [max[0].max(p[0]), max[1].max(p[1])];
+    }
+
+    // The grid is centered on the contour, so the margins are the same on opposite sides.
+    let axis = |axis: usize| {
+        let n_cells = ((max[axis] - min[axis]) / cell).floor().max(0.0);
+        let start = min[axis] + (max[axis] - min[axis] - n_cells * cell) / 2.0;
+        (0..=n_cells as usize).map(move |i| start + i as f32 * cell)
+    };
+
+    let near_contour = |p: [f32; 2]| loops.iter().cloned().any(|range| {
+        let (first, last) = (range.start, range.end - 1);
+        range.map(|i| (ring[i], ring[if i == last { first } else { i + 1 }]))
+            .any(|(a, b)| contour::distance_to_segment(p, a, b) < cell / 2.0)
+    });
+    let in_cap = |p: [f32; 2]| triangles.iter().any(|t| in_triangle(t.map(|i| ring[i]), p));
+
+    axis(1).flat_map(|y| axis(0).map(move |x| [x, y]))
+        .filter(|p| !near_contour(*p) && in_cap(*p))
+        .collect()
+}
+
+/// Adds the points from `first_interior` on, which lie inside the triangulated area, to the triangles, for
+/// `CapFill::Grid`. Every point splits the triangle it lies in, or the two triangles on either side of the edge it
+/// lies on. Edges are then flipped until no triangle has a point of its neighbours inside its circumcircle, which
+/// gives the evenest triangles the points allow. The edges of the contour only have a triangle on one side, so
+/// they are never flipped.
+fn insert_interior_points(triangles: &mut Vec<[usize; 3]>, points: &[[f32; 2]], first_interior: usize) {
+    if first_interior >= points.len() {
+        return;
+    }
+
+    // The triangles all wind the same way, so the neighbour across an edge has the same edge reversed.
+    let mut edges: HashMap<(usize, usize), usize> = triangles.iter().enumerate()
+        .flat_map(|(t, [v0, v1, v2])| [((*v0, *v1), t), ((*v1, *v2), t), ((*v2, *v0), t)])
+        .collect();
+    let set = |triangles: &mut Vec<[usize; 3]>, edges: &mut HashMap<(usize, usize), usize>, t: usize, triangle: [usize; 3]| {
+        if t == triangles.len() {
+            triangles.push(triangle);
+        }
+        triangles[t] = triangle;
+        let [v0, v1, v2] = triangle;
+        for edge in [(v0, v1), (v1, v2), (v2, v0)] {
+            edges.insert(edge, t);
+        }
+    };
+
+    let mut unchecked: Vec<(usize, usize)> = vec![];
+    let mut last = 0;
+    for p in first_interior..points.len() {
+        // The points come in rows, so the next one is usually close to the triangles of the last one.
+        let Some(t) = locate(triangles, &edges, points, points[p], last) else { continue };
+        last = t;
+        let [a, b, c] = triangles[t];
+
+        // The edge the point lies on, if any, as the triangle would have a corner without area there.
+        let on_edge = [(a, b, c), (b, c, a), (c, a, b)].into_iter().find(|(x, y, _)| {
+            let (x, y) = (points[*x], points[*y]);
+            orientation(x, y, points[p]).abs() <= 1e-6 * distance_2d(x, y).powi(2)
+        });
+        match on_edge {
+            None => {
+                set(triangles, &mut edges, t, [a, b, p]);
+                set(triangles, &mut edges, triangles.len(), [b, c, p]);
+                set(triangles, &mut edges, triangles.len(), [c, a, p]);
+                unchecked.extend([(a, b), (b, c), (c, a)]);
+            }
+            Some((x, y, w)) => {
+                let Some(&u) = edges.get(&(y, x)) else { continue };
+                let z = triangles[u].into_iter().find(|v| *v != x && *v != y).unwrap_or(x);
+                edges.remove(&(x, y));
+                edges.remove(&(y, x));
+                set(triangles, &mut edges, t, [x, p, w]);
+                set(triangles, &mut edges, triangles.len(), [p, y, w]);
+                set(triangles, &mut edges, u, [y, p, z]);
+                set(triangles, &mut edges, triangles.len(), [p, x, z]);
+                unchecked.extend([(y, w), (w, x), (x, z), (z, y)]);
+            }
+        }
+        flip_to_delaunay(triangles, &mut edges, points, &mut unchecked);
+    }
+
+    // The triangles of the contour alone can be long and thin, also away from the inserted points.
+    unchecked.extend(triangles.iter().flat_map(|[v0, v1, v2]| [(*v0, *v1), (*v1, *v2), (*v2, *v0)]));
+    flip_to_delaunay(triangles, &mut edges, points, &mut unchecked);
+}
+
+/// The triangle that `p` lies in, found by walking from triangle `start` across the edges that `p` lies beyond.
+/// If the walk leaves the triangles or goes round in circles, every triangle is searched instead.
+fn locate(triangles: &[[usize; 3]], edges: &HashMap<(usize, usize), usize>, points: &[[f32; 2]], p: [f32; 2], start: usize) -> Option<usize> {
+    let mut t = start;
+    for _ in 0..triangles.len() {
+        let [a, b, c] = triangles[t];
+        let winding = orientation(points[a], points[b], points[c]);
+        let beyond = [(a, b), (b, c), (c, a)].into_iter().find(|(x, y)| orientation(points[*x], points[*y], p) * winding < 0.0);
+        let Some((x, y)) = beyond else {
+            if winding != 0.0 {
+                return Some(t);
+            }
+            break;
+        };
+        let Some(&next) = edges.get(&(y, x)) else { break };
+        t = next;
+    }
+    (0..triangles.len()).find(|t| in_triangle(triangles[*t].map(|i| points[i]), p))
+}
+
+/// Flips the `unchecked` edges, and then the edges around every flipped edge, whose triangles have the opposite
+/// point of the neighbour across the edge inside their circumcircle, for `insert_interior_points`.
+fn flip_to_delaunay(triangles: &mut [[usize; 3]], edges: &mut HashMap<(usize, usize), usize>, points: &[[f32; 2]], unchecked: &mut Vec<(usize, usize)>) {
+    // Flipping always ends, but rounding could make it go back and forth between nearly cocircular points.
+    let mut flips_left = triangles.len() * triangles.len();
+
+    while let Some((a, b)) = unchecked.pop() {
+        let (Some(&t), Some(&u)) = (edges.get(&(a, b)), edges.get(&(b, a))) else { continue };
+        let c = triangles[t].into_iter().find(|v| *v != a && *v != b).unwrap_or(a);
+        let d = triangles[u].into_iter().find(|v| *v != a && *v != b).unwrap_or(a);
+
+        let [pa, pb, pc, pd] = [a, b, c, d].map(|i| points[i]);
+        let winding = orientation(pa, pb, pc);
+        let convex = orientation(pa, pd, pc) * winding > 0.0 && orientation(pd, pb, pc) * winding > 0.0;
+        if !convex || !in_circumcircle(pa, pb, pc, pd) || flips_left == 0 {
+            continue;
+        }
+        flips_left -= 1;
+
+        // The quad runs a, d, b, c in the winding of the triangles, and is split along c-d instead.
+        edges.remove(&(a, b));
+        edges.remove(&(b, a));
+        triangles[t] = [a, d, c];
+        triangles[u] = [d, b, c];
+        for (edge, triangle) in [((a, d), t), ((d, c), t), ((c, a), t), ((d, b), u), ((b, c), u), ((c, d), u)] {
+            edges.insert(edge, triangle);
+        }
+        unchecked.extend([(a, d), (d, b), (b, c), (c, a)]);
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `c`, positive if it's counter-clockwise.
+fn orientation(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// Whether `p` lies inside the triangle or on its edges, whichever way it winds. Triangles without area contain no points.
+fn in_triangle([a, b, c]: [[f32; 2]; 3], p: [f32; 2]) -> bool {
+    let turns = [orientation(a, b, p), orientation(b, c, p), orientation(c, a, p)];
+    orientation(a, b, c) != 0.0 && (turns.iter().all(|turn| *turn >= 0.0) || turns.iter().all(|turn| *turn <= 0.0))
+}
+
+/// Whether `d` lies inside the circumcircle of the triangle `a`, `b`, `c`, whichever way it winds. Points on the
+/// circle, within rounding, don't count, so the four corners of a grid cell are left as they are.
+fn in_circumcircle(a: [f32; 2], b: [f32; 2], c: [f32; 2], d: [f32; 2]) -> bool {
+    let [a, b, c] = [a, b, c].map(|p| [(p[0] - d[0]) as f64, (p[1] - d[1]) as f64]);
+    let lift = |p: [f64; 2]| p[0] * p[0] + p[1] * p[1];
+    let det = lift(a) * (b[0] * c[1] - c[0] * b[1])
+        - lift(b) * (a[0] * c[1] - c[0] * a[1])
+        + lift(c) * (a[0] * b[1] - b[0] * a[1]);
+    let winding = ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])).signum();
+    let scale = lift(a).max(lift(b)).max(lift(c));
+    det * winding > 1e-6 * scale * scale
+}
+
+fn earcut(outline: &Outline, n_points: usize) -> Result<Vec<[usize; 3]>, MeshError> {
+    let mut coordinates: Vec<f32> = vec![];
+    let mut hole_indices: Vec<usize> = vec![];
+    for contour in outline.iter() {
+        if !coordinates.is_empty() {
+            hole_indices.push(coordinates.len() / 2);
+        }
+        coordinates.extend(contour.iter().flatten());
+    }
+
+    let indices = earcutr::earcut(&coordinates, &hole_indices, 2).map_err(|_| MeshError::TriangulationFailed { points: n_points })?;
+
+    let point = |i: usize| [coordinates[2 * i], coordinates[2 * i + 1]];
+    Ok(indices.chunks(3).map(|t| {
+        let (p0, p1, p2) = (point(t[0]), point(t[1]), point(t[2]));
+        let cross = (p1[0] - p0[0]) * (p2[1] - p0[1]) - (p1[1] - p0[1]) * (p2[0] - p0[0]);
+        if cross < 0.0 { [t[0], t[2], t[1]] } else { [t[0], t[1], t[2]] }
+    }).collect())
+}
+
+// Bevel offsets are allowed to grow to this many times the requested inset at sharp corners.
+const BEVEL_MITER_LIMIT: f32 = 2.0;
+
+/// Offsets every point of `contour` by `distance` towards the inside of the shape, along the bisector of its two edges.
+///
+/// Offsetting a contour can make it intersect itself, which would break the triangulation of the caps.
+/// This happens at tight corners, so two fallbacks are used there:
+/// * The offset at a corner is limited to `BEVEL_MITER_LIMIT` times `distance`, instead of growing without bound.
+/// * The offset of a point never exceeds half of its shortest adjacent edge, so neighbouring points can't cross.
+///
+/// Points with a degenerate edge or a complete turn are left where they are.
+fn inset_contour(contour: &[[f32; 2]], loops: &[Range<usize>], inward_sign: f32, distance: f32) -> Vec<[f32; 2]> {
+    let mut inset = contour.to_vec();
+
+    for range in loops.iter().cloned() {
+        let (first, last) = (range.start, range.end - 1);
+
+        for i in range {
+            let prev = if i == first {last} else {i - 1};
+            let next = if i == last {first} else {i + 1};
+
+            let (p0, p1, p2) = (contour[prev], contour[i], contour[next]);
+            let d0 = normal_of_line(p0, p1);
+            let d1 = normal_of_line(p1, p2);
+            let n0 = [-inward_sign * d0[1], inward_sign * d0[0]];
+            let n1 = [-inward_sign * d1[1], inward_sign * d1[0]];
+
+            let bisector = normalize_2d([n0[0] + n1[0], n0[1] + n1[1]]);
+            if !bisector.iter().chain(&n0).all(|c| c.is_finite()) {
+                continue;
+            }
+
+            let cos_half_angle = (bisector[0] * n0[0] + bisector[1] * n0[1]).max(1.0 / BEVEL_MITER_LIMIT);
+            let shortest_edge = distance_2d(p0, p1).min(distance_2d(p1, p2));
+            let offset = (distance / cos_half_angle).min(shortest_edge / 2.0);
+
+            inset[i] = [p1[0] + bisector[0] * offset, p1[1] + bisector[1] * offset];
+        }
+    }
+
+    inset
+}
+
+fn normalize_2d(v: [f32; 2]) -> [f32; 2] {
+    let len = (v[0] * v[0] + v[1] * v[1]).sqrt();
+    [v[0] / len, v[1] / len]
+}
+
+fn distance_2d(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
 }
 
 fn normal_of_line(v0: [f32; 2], v1: [f32; 2]) -> [f32; 2] {
@@ -121,100 +2039,272 @@ fn normal_of_line(v0: [f32; 2], v1: [f32; 2]) -> [f32; 2] {
 ///
 /// # Returns
 ///
-/// A `Result` which is `Ok` if the mesh was created and saved successfully, or an `Err` containing a boxed error.
+/// A `Result` which is `Ok` if the mesh was created and saved successfully, or an `Err` containing a `MeshError`.
+#[cfg(feature = "io")]
 pub fn create_and_save_mesh_from_image(
     img: &DynamicImage,
-    file_path: &str,
+    file_path: impl AsRef<Path>,
     params: Params,
-) -> Result<(), Box<dyn Error>> {
-    let mesh = create_mesh_from_image(img, params)?;
-    save_mesh_to_file(mesh, file_path)
+) -> Result<(), MeshError> {
+    let file_path = file_path.as_ref();
+    let materials = params.materials.clone();
+    let export = params.export;
+    let premultiplied_alpha = params.premultiplied_alpha;
+    let (mesh, uv_bounds) = mesh_and_uv_bounds_from_image(img, params, &|_| {})?;
+    save_mesh_to_file_with_options(mesh, file_path, materials.clone(), export)?;
+
+    if export.write_texture {
+        let texture_path = match &materials.texture_path {
+            Some(texture_path) => file_path.parent().unwrap_or(Path::new("")).join(texture_path),
+            None => file_path.with_extension("png"),
+        };
+        if let Some(parent) = texture_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        texture_image(img, uv_bounds, premultiplied_alpha).save(texture_path)?;
+    }
+    Ok(())
 }
 
-fn triangle_from_indices(v0: usize, v1: usize, v2: usize) -> Primitive {
-    Primitive::Triangle(
-        (v0, Some(v0), Some(v0)),
-        (v1, Some(v1), Some(v1)),
-        (v2, Some(v2), Some(v2)),
-    )
+/// The OBJ file, MTL file and texture of a mesh, as created by `mesh_to_obj_bytes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjBundle {
+    /// The OBJ text, which refers to the material library as `mesh.mtl`.
+    pub obj: String,
+    /// The MTL text, which refers to the texture as `MaterialParams::texture_path`, or `mesh.png` if it's `None`.
+    pub mtl: String,
+    /// The PNG encoded texture. With `UvMode::BoundingBox`, only the part of the image covered by the uvs.
+    pub texture_png: Vec<u8>,
 }
 
-/// Saves a mesh to a OBJ file.
+/// Creates a mesh from an encoded image, and returns the OBJ file, MTL file and texture in memory,
+/// without touching the file system. Saving them as `mesh.obj`, `mesh.mtl` and `mesh.png` gives the same
+/// files as `create_and_save_mesh_from_image` with `ExportOptions::write_texture` set.
 ///
 /// # Arguments
 ///
-/// * `mesh` - The mesh to save.
-/// * `file_path` - The file path to save the mesh to. Has to end with `.obj`.
+/// * `img_bytes` - The encoded image, in any format the `image` crate can guess from its contents.
+/// * `params` - Parameters for creating the mesh. `ExportOptions::write_texture` is not used, the texture is always returned.
 ///
 /// # Returns
 ///
-/// A `Result` which is `Ok` if the mesh was saved successfully, or an `Err` containing a boxed error.
-pub fn save_mesh_to_file(mesh: Mesh, file_path: &str) -> Result<(), Box<dyn Error>> {
-    let shapes = mesh.triangles.iter().map(|triangle| {
-        Shape {
-            primitive: *triangle,
-            groups: vec![],
-            smoothing_groups: vec![],
+/// A `Result` containing the `ObjBundle`, or an `Err` containing a `MeshError` if the image can't be decoded
+/// or the mesh can't be created.
+pub fn mesh_to_obj_bytes(img_bytes: &[u8], params: Params) -> Result<ObjBundle, MeshError> {
+    let img = image::load_from_memory(img_bytes)?;
+    let materials = params.materials.clone();
+    let export = params.export;
+    let premultiplied_alpha = params.premultiplied_alpha;
+    let (mesh, uv_bounds) = mesh_and_uv_bounds_from_image(&img, params, &|_| {})?;
+
+    let mut obj = vec![];
+    obj::write_mesh_obj_with_decimals(&mesh, &mut obj, "mesh.mtl", export.decimals)?;
+    let mut mtl = vec![];
+    obj::write_mtl(&mut mtl, &materials, materials.texture_path.as_deref().unwrap_or("mesh.png"))?;
+    let mut texture_png = vec![];
+    texture_image(&img, uv_bounds, premultiplied_alpha)
+        .write_to(&mut std::io::Cursor::new(&mut texture_png), image::ImageFormat::Png)?;
+
+    Ok(ObjBundle {
+        // Everything written is ASCII, apart from the texture path, which came from a `String`.
+        obj: String::from_utf8(obj).expect("the OBJ text is valid UTF-8"),
+        mtl: String::from_utf8(mtl).expect("the MTL text is valid UTF-8"),
+        texture_png,
+    })
+}
+
+/// The texture of a mesh whose uvs cover `uv_bounds` of the image, with its colors unpremultiplied if
+/// `premultiplied_alpha` is set. Floating point images are converted to 16 bits per channel, which PNG can store.
+fn texture_image(img: &DynamicImage, uv_bounds: [[f32; 2]; 2], premultiplied_alpha: bool) -> DynamicImage {
+    let texture = crop_to_uv_bounds(img, uv_bounds);
+    match texture {
+        _ if premultiplied_alpha && texture.color().has_alpha() => {
+            let mut rgba = texture.into_rgba8();
+            rgba.pixels_mut().for_each(|pixel| *pixel = unpremultiply(*pixel));
+            DynamicImage::ImageRgba8(rgba)
         }
-    });
+        DynamicImage::ImageRgb32F(_) => DynamicImage::ImageRgb16(texture.into_rgb16()),
+        DynamicImage::ImageRgba32F(_) => DynamicImage::ImageRgba16(texture.into_rgba16()),
+        texture => texture,
+    }
+}
 
-    let geometry = Geometry {
-        material_name: Some("material".to_string()),
-        shapes: shapes.collect(),
-    };
+/// Divides the color of a pixel by its alpha, undoing premultiplied alpha. Fully transparent pixels are left as they are.
+fn unpremultiply(pixel: image::Rgba<u8>) -> image::Rgba<u8> {
+    let [r, g, b, a] = pixel.0;
+    if a == 0 {
+        return pixel;
+    }
+    let divide = |c: u8| ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+    image::Rgba([divide(r), divide(g), divide(b), a])
+}
 
-    let obj = Object {
-        name: std::path::Path::new(file_path)
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("default")
-            .to_string(),
-        vertices: mesh.vertices,
-        tex_vertices: mesh.uv_vertices,
-        normals: mesh.normals,
-        geometry: vec![geometry],
-    };
-    
-    let mtl_file_path = file_path.replace(".obj", ".mtl");
-    // Extract the filename + extension from the mtl_file_path
-    let mtl_filename = std::path::Path::new(&mtl_file_path)
-    .file_name()
-    .and_then(|s| s.to_str())
-    .unwrap_or("material.mtl");
+/// Returns the part of the image that the [0, 1] uv square covers. Parts of the bounds outside the image,
+/// where the contour is offset past its edge, are transparent.
+fn crop_to_uv_bounds(img: &DynamicImage, [min, max]: [[f32; 2]; 2]) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let (f_width, f_height) = (width as f32, height as f32);
+    let [x0, y0] = [(min[0] * f_width).round() as i64, (min[1] * f_height).round() as i64];
+    let [x1, y1] = [(max[0] * f_width).round() as i64, (max[1] * f_height).round() as i64];
+    if [x0, y0, x1, y1] == [0, 0, width as i64, height as i64] {
+        return img.clone();
+    }
+
+    let mut cropped = image::RgbaImage::new((x1 - x0).max(1) as u32, (y1 - y0).max(1) as u32);
+    for (x, y, pixel) in cropped.enumerate_pixels_mut() {
+        let (sx, sy) = (x as i64 + x0, y as i64 + y0);
+        if (0..width as i64).contains(&sx) && (0..height as i64).contains(&sy) {
+            *pixel = img.get_pixel(sx as u32, sy as u32);
+        }
+    }
+    DynamicImage::ImageRgba8(cropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Extract the filename + extension from the mtl_file_path
-    let png_filename = mtl_filename.replace(".mtl", ".png");
+    /// A `size` by `size` image of an opaque ring, a disc with a round hole in the middle.
+    fn ring_image(size: u32) -> DynamicImage {
+        let center = size as f32 / 2.0;
+        image::RgbaImage::from_fn(size, size, |x, y| {
+            let r = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+            let alpha = if r < 0.4 * size as f32 && r > 0.15 * size as f32 { 255 } else { 0 };
+            image::Rgba([200, 100, 50, alpha])
+        }).into()
+    }
+
+    fn png_bytes(image: &DynamicImage) -> Vec<u8> {
+        let mut bytes = vec![];
+        image.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png).unwrap();
+        bytes
+    }
 
+    #[test]
+    fn normal_indices_exist_with_holes_and_side_rings() {
+        let params = Params::builder()
+            .bevel(BevelParams { width: 0.01, segments: 3 })
+            .wall_subdivisions(2)
+            .build();
+        let image = ring_image(128);
+        let outline = contour::find_outline_from_transparency_with_offset(&image, params.contour_params).unwrap();
+        assert_eq!(outline.holes.len(), 1);
+        let mesh = create_mesh_from_image(&image, params).unwrap();
 
-    let obj_set = ObjSet {
-        material_library: Some(mtl_file_path.clone()),
-        objects: vec![obj],
-    };
+        assert!(mesh.surfaces.contains(&Surface::Side));
+        for triangle in &mesh.triangles {
+            let Primitive::Triangle(c0, c1, c2) = triangle else { continue };
+            for (_, _, n) in [c0, c1, c2] {
+                assert!(n.is_some_and(|n| n < mesh.normals.len()), "normal {:?} of {}", n, mesh.normals.len());
+            }
+        }
+    }
 
-    let mut mtl_file = File::create(&mtl_file_path)?;
-    writeln!(mtl_file, "newmtl material")?;
-    writeln!(mtl_file, "map_Kd {}", png_filename)?;
+    #[test]
+    fn bow_tie_contour_is_self_intersecting() {
+        let bow_tie = Contour::from_points(vec![[0.2, 0.2], [0.8, 0.8], [0.8, 0.2], [0.2, 0.8]]).unwrap();
+        assert!(!bow_tie.is_simple());
 
-    obj_exporter::export_to_file(&obj_set, file_path).map_err(|e| Box::new(e) as Box<dyn Error>)?;
+        let outline = Outline { outer: bow_tie, holes: vec![] };
+        let result = mesh_from_outlines(&[outline], &[], (64, 64), Params::builder().build(), None, &|_| {});
+        assert!(matches!(result, Err(MeshError::SelfIntersectingContour)));
+    }
 
-    // Open the file at file_path and read its contents
-    let mut obj_file = File::open(file_path)?;
-    let mut obj_contents = String::new();
-    obj_file.read_to_string(&mut obj_contents)?;
+    #[test]
+    fn flipping_twice_restores_triangles_and_normals() {
+        let image = ring_image(64);
+        let mesh = create_mesh_from_image(&image, Params::builder().build()).unwrap();
+        let flipped = create_mesh_from_image(&image, Params::builder().build()).unwrap().flip_winding();
+        assert_ne!(flipped.triangles, mesh.triangles);
 
-    
+        let restored = flipped.flip_winding();
+        let normals = |mesh: &Mesh| mesh.normals.iter().map(|n| [n.x, n.y, n.z]).collect::<Vec<_>>();
+        assert_eq!(restored.triangles, mesh.triangles);
+        assert_eq!(normals(&restored), normals(&mesh));
+    }
 
-    // Prepend "mtllib {mtl_filename}" to the contents
-    let mut new_contents = format!("mtllib {}\n{}", mtl_filename, obj_contents);
+    #[test]
+    fn obj_without_uvs_has_no_texture_coordinates() {
+        let params = Params::builder().include_uvs(false).build();
+        let bundle = mesh_to_obj_bytes(&png_bytes(&ring_image(64)), params).unwrap();
 
-    // Add "usemtl material" before the first line that starts with 'f'
-    if let Some(pos) = new_contents.find("\nf") {
-        let (before, after) = new_contents.split_at(pos + 1);
-        new_contents = format!("{}\nusemtl material\n{}", before, after);
+        assert!(bundle.obj.lines().any(|line| line.starts_with("f ")));
+        assert!(!bundle.obj.lines().any(|line| line.starts_with("vt ")));
+        for line in bundle.obj.lines().filter(|line| ["f ", "l ", "p "].iter().any(|k| line.starts_with(k))) {
+            for corner in line.split_whitespace().skip(1) {
+                assert!(corner.split('/').nth(1).is_none_or(str::is_empty), "{} has a uv", corner);
+            }
+        }
     }
-    // Write the new contents back to the file
-    let mut obj_file = File::create(file_path)?;
-    obj_file.write_all(new_contents.as_bytes())?;
 
-    Ok(())
-}
\ No newline at end of file
+    #[test]
+    fn merged_meshes_are_valid() {
+        let a = create_mesh_from_image(&ring_image(64), Params::builder().build()).unwrap();
+        let b = create_mesh_from_image(&ring_image(128), Params::builder().thickness(0.2).build()).unwrap();
+        let (vertices, triangles) = (a.vertices.len() + b.vertices.len(), a.triangles.len() + b.triangles.len());
+
+        let merged = Mesh::merge(vec![a, b]);
+
+        assert_eq!(merged.vertices.len(), vertices);
+        assert_eq!(merged.triangles.len(), triangles);
+        assert_eq!(merged.surfaces.len(), triangles);
+        merged.validate().unwrap();
+    }
+
+    #[test]
+    fn contour_with_duplicated_points_meshes() {
+        let contour = Contour::from_points(vec![
+            [0.2, 0.2], [0.2, 0.2], [0.8, 0.2], [0.8, 0.2], [0.8, 0.2],
+            [0.8, 0.8], [0.2, 0.8], [0.2, 0.8], [0.2, 0.2],
+        ]).unwrap();
+
+        // The last point repeats the first, across the edge that closes the contour.
+        assert_eq!(contour.clone().dedup(1e-6).into_points(), vec![[0.2, 0.2], [0.8, 0.2], [0.8, 0.8], [0.2, 0.8]]);
+
+        let outline = Outline { outer: contour, holes: vec![] };
+        let (mesh, _) = mesh_from_outlines(&[outline], &[], (64, 64), Params::builder().build(), None, &|_| {}).unwrap();
+        mesh.validate().unwrap();
+    }
+
+    #[test]
+    fn preserve_aspect_keeps_the_ratio_of_a_wide_image() {
+        // Fully opaque, so the padding gives the shape the edge of the image. The shape grows about a pixel into the
+        // padding on every side, which makes it a little less than 4 times as wide as it's high.
+        let image = image::RgbaImage::from_pixel(40, 10, image::Rgba([255, 255, 255, 255])).into();
+        let ratio = |preserve_aspect| {
+            let params = Params::builder().preserve_aspect(preserve_aspect).pad_border(1).build();
+            let (min, max) = create_mesh_from_image(&image, params).unwrap().bounding_box();
+            (max[0] - min[0]) / (max[1] - min[1])
+        };
+
+        let (preserved, stretched) = (ratio(true), ratio(false));
+        assert!((preserved - 4.0).abs() < 0.75, "{}", preserved);
+        assert!((preserved / stretched - 4.0).abs() < 1e-6, "{} {}", preserved, stretched);
+    }
+
+    #[test]
+    fn obj_output_is_the_same_every_run() {
+        // Two shapes, so the order of the outlines traced in parallel matters too.
+        let ring = ring_image(64).into_rgba8();
+        let mut image = image::RgbaImage::new(128, 64);
+        image::imageops::replace(&mut image, &ring, 0, 0);
+        image::imageops::replace(&mut image, &ring, 64, 0);
+        let bytes = png_bytes(&image.into());
+        let params = || Params::builder().all_shapes(true).weld_seams(true).build();
+
+        let first = mesh_to_obj_bytes(&bytes, params()).unwrap();
+        let second = mesh_to_obj_bytes(&bytes, params()).unwrap();
+
+        assert!(first.obj.contains("\nf "));
+        assert_eq!(first.obj, second.obj);
+    }
+
+    #[test]
+    fn fully_opaque_image_meshes_with_padding() {
+        let image = image::RgbaImage::from_pixel(16, 16, image::Rgba([255, 255, 255, 255])).into();
+
+        assert!(matches!(create_mesh_from_image(&image, Params::builder().build()), Err(MeshError::FullyOpaque)));
+        let mesh = create_mesh_from_image(&image, Params::builder().pad_border(1).build()).unwrap();
+        mesh.validate().unwrap();
+    }
+}
@@ -1,43 +1,297 @@
-use image_to_mesh::{create_and_save_mesh_from_image, Params};
+use image_to_mesh::{
+    contour::MaskSource, create_mesh_from_image_with_progress, save_mesh_to_file, save_mesh_to_glb, save_mesh_to_ply,
+    save_mesh_to_stl, Mesh, Params, Progress,
+};
+use rayon::prelude::*;
+use image::DynamicImage;
+use std::cell::RefCell;
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::error::Error;
+use std::time::Instant;
 
-fn process_image(image_path: &Path) -> Result<(), Box<dyn Error>> {
-    let save_path = image_path.with_extension("obj");
-    let img = image::open(image_path)?;
-    create_and_save_mesh_from_image(&img, save_path.to_str().unwrap(), Params::default())?;
+// Image formats the CLI converts unless `--formats` says otherwise.
+const DEFAULT_FORMATS: &[&str] = &["png", "jpg", "jpeg", "webp", "bmp", "tga"];
+
+/// The file format the meshes are saved in.
+#[derive(Clone, Copy)]
+enum OutputFormat {
+    /// OBJ, with an MTL file and the texture next to it.
+    Obj,
+    /// Binary STL, without uvs or texture.
+    Stl,
+    /// ASCII PLY, without texture.
+    Ply,
+    /// Binary glTF, with the texture embedded.
+    Glb,
+}
+
+impl OutputFormat {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "obj" => Some(OutputFormat::Obj),
+            "stl" => Some(OutputFormat::Stl),
+            "ply" => Some(OutputFormat::Ply),
+            "glb" => Some(OutputFormat::Glb),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Obj => "obj",
+            OutputFormat::Stl => "stl",
+            OutputFormat::Ply => "ply",
+            OutputFormat::Glb => "glb",
+        }
+    }
+}
+
+struct Args {
+    input_path: PathBuf,
+    jobs: Option<usize>,
+    /// Lowercase file extensions of the images to convert.
+    formats: Vec<String>,
+    format: OutputFormat,
+    out_dir: Option<PathBuf>,
+    recursive: bool,
+    /// Whether to print how long each step took for every image.
+    time: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut input_path = None;
+    let mut jobs = None;
+    let mut formats: Vec<String> = DEFAULT_FORMATS.iter().map(|f| f.to_string()).collect();
+    let mut format = OutputFormat::Obj;
+    let mut out_dir = None;
+    let mut recursive = false;
+    let mut time = false;
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--jobs" => {
+                let value = iter.next().ok_or("--jobs requires a value")?;
+                let value = value.parse::<usize>().ok().filter(|n| *n > 0).ok_or("--jobs requires a positive number")?;
+                jobs = Some(value);
+            }
+            "--formats" => {
+                let value = iter.next().ok_or("--formats requires a value")?;
+                formats = value.split(',').map(|f| f.trim().trim_start_matches('.').to_lowercase()).filter(|f| !f.is_empty()).collect();
+                if formats.is_empty() {
+                    return Err("--formats requires at least one extension".into());
+                }
+            }
+            "--format" => {
+                let value = iter.next().ok_or("--format requires a value")?;
+                format = OutputFormat::parse(value).ok_or("--format must be one of obj, stl, ply or glb")?;
+            }
+            "--out-dir" => {
+                let value = iter.next().ok_or("--out-dir requires a value")?;
+                out_dir = Some(PathBuf::from(value));
+            }
+            "--recursive" => recursive = true,
+            "--time" => time = true,
+            _ if input_path.is_none() => input_path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    Ok(Args {
+        input_path: input_path.ok_or("Missing image path or directory")?,
+        jobs,
+        formats,
+        format,
+        out_dir,
+        recursive,
+        time,
+    })
+}
+
+fn has_supported_extension(path: &Path, formats: &[String]) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|extension| formats.iter().any(|f| f.eq_ignore_ascii_case(extension)))
+}
+
+/// Finds the images with a supported extension in `dir`, and in its subdirectories if `recursive` is set.
+/// Directories that can't be read are reported and skipped. The images are sorted by path.
+fn find_images(dir: &Path, formats: &[String], recursive: bool) -> Vec<PathBuf> {
+    let mut images = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.is_dir() {
+                if recursive {
+                    dirs.push(path);
+                }
+            } else if has_supported_extension(&path, formats) {
+                images.push(path);
+            }
+        }
+    }
+
+    // `read_dir` lists the files in whatever order the file system keeps them.
+    images.sort();
+    images
+}
+
+/// Returns where the mesh of `image_path` is saved. Without an output directory, it's saved next to the image.
+/// Otherwise it's saved in `out_dir`, in the same place relative to it as the image is relative to `input_root`.
+/// Either way, it gets the extension of `format`.
+fn output_path(image_path: &Path, input_root: &Path, out_dir: Option<&Path>, format: OutputFormat) -> PathBuf {
+    let Some(out_dir) = out_dir else {
+        return image_path.with_extension(format.extension());
+    };
+
+    let relative = image_path.strip_prefix(input_root).ok()
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .or_else(|| image_path.file_name().map(Path::new))
+        .unwrap_or(image_path);
+    out_dir.join(relative).with_extension(format.extension())
+}
+
+/// The steps of converting an image and when each of them started, for `--time`.
+struct Stages(RefCell<Vec<(&'static str, Instant)>>);
+
+impl Stages {
+    fn new() -> Self {
+        Stages(RefCell::new(vec![("load", Instant::now())]))
+    }
+
+    fn start(&self, name: &'static str) {
+        self.0.borrow_mut().push((name, Instant::now()));
+    }
+
+    /// How long every step took, up to now, e.g. `load 1.2 ms, sdf 3.4 ms, ..., total 9.9 ms`.
+    fn summary(&self) -> String {
+        let stages = self.0.borrow();
+        let end = Instant::now();
+        let ends = stages.iter().skip(1).map(|(_, start)| *start).chain([end]);
+        let mut parts: Vec<String> = stages.iter().zip(ends)
+            .map(|((name, start), end)| format!("{} {:.1} ms", name, (end - *start).as_secs_f64() * 1000.0))
+            .collect();
+        parts.push(format!("total {:.1} ms", (end - stages[0].1).as_secs_f64() * 1000.0));
+        parts.join(", ")
+    }
+}
+
+fn process_image(image_path: &Path, save_path: &Path, format: OutputFormat, write_texture: bool, time: bool) -> Result<(), Box<dyn Error>> {
+    let stages = Stages::new();
+    let img = image::open(image_path).map_err(|e| format!("can't read the image: {}", e))?;
+
+    let report = |progress: Progress| {
+        match progress {
+            Progress::SdfStarted => stages.start("sdf"),
+            Progress::TracingStarted => stages.start("trace"),
+            Progress::ContourTraced { .. } => stages.start("smooth"),
+            Progress::Smoothing { iteration, total } if iteration == total => stages.start("simplify"),
+            Progress::Triangulating => stages.start("triangulate"),
+            _ => {}
+        }
+
+        // Only the last smoothing iteration is shown, to keep the output to a few lines per file.
+        if let Progress::Smoothing { iteration, total } = progress {
+            if iteration < total { return };
+        }
+        eprintln!("{}: {}", image_path.display(), progress);
+    };
+
+    // Images without an alpha channel, like JPEGs, are meshed from their bright parts instead.
+    let params = match img.color().has_alpha() {
+        true => Params::default(),
+        false => Params::builder().mask_source(MaskSource::Luminance).build(),
+    };
+
+    let mesh = create_mesh_from_image_with_progress(&img, params, Some(&report))?;
+    stages.start("write");
+    save(mesh, img, save_path, format, write_texture).map_err(|e| format!("can't save {}: {}", save_path.display(), e))?;
+
+    if time {
+        eprintln!("{}: {}", image_path.display(), stages.summary());
+    }
+    Ok(())
+}
+
+/// Saves `mesh` in `format`, with `img` as its texture.
+fn save(mesh: Mesh, img: DynamicImage, save_path: &Path, format: OutputFormat, write_texture: bool) -> Result<(), Box<dyn Error>> {
+    // The image is saved as a PNG texture, and PNG has no floating point formats.
+    let img = match img {
+        DynamicImage::ImageRgb32F(_) => DynamicImage::ImageRgb16(img.into_rgb16()),
+        DynamicImage::ImageRgba32F(_) => DynamicImage::ImageRgba16(img.into_rgba16()),
+        img => img,
+    };
+    if let Some(parent) = save_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match format {
+        OutputFormat::Obj => {
+            save_mesh_to_file(mesh, save_path)?;
+
+            // The material refers to a PNG next to the mesh, which is the image itself unless the mesh is saved elsewhere.
+            if write_texture {
+                img.save(save_path.with_extension("png"))?;
+            }
+        }
+        OutputFormat::Stl => save_mesh_to_stl(mesh, save_path, false)?,
+        OutputFormat::Ply => save_mesh_to_ply(&mesh, save_path)?,
+        OutputFormat::Glb => {
+            let mut texture_png = vec![];
+            img.write_to(&mut std::io::Cursor::new(&mut texture_png), image::ImageFormat::Png)?;
+            save_mesh_to_glb(&mesh, &texture_png, save_path)?;
+        }
+    }
     Ok(())
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: {} <image_path_or_directory>", args[0]);
-        std::process::exit(1);
+    let args = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!("Usage: {} [--jobs N] [--formats png,jpg,...] [--format obj|stl|ply|glb] [--out-dir DIR] [--recursive] [--time] <image_path_or_directory>", args[0]);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global().unwrap();
     }
 
-    let input_path = Path::new(&args[1]);
+    let input_path = args.input_path.as_path();
 
     if input_path.is_dir() {
-        for entry in fs::read_dir(input_path).unwrap() {
-            let entry = entry.unwrap();
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("png") {
-                if let Err(e) = process_image(&path) {
-                    eprintln!("Error processing {}: {}", path.display(), e);
-                }
+        let paths = find_images(input_path, &args.formats, args.recursive);
+
+        paths.par_iter().for_each(|path| {
+            let save_path = output_path(path, input_path, args.out_dir.as_deref(), args.format);
+            if let Err(e) = process_image(path, &save_path, args.format, args.out_dir.is_some(), args.time) {
+                eprintln!("Skipping {}: {}", path.display(), e);
             }
-        }
+        });
     } else if input_path.is_file() {
-        if input_path.extension().and_then(|s| s.to_str()) == Some("png") {
-            if let Err(e) = process_image(input_path) {
+        if has_supported_extension(input_path, &args.formats) {
+            let save_path = output_path(input_path, input_path, args.out_dir.as_deref(), args.format);
+            if let Err(e) = process_image(input_path, &save_path, args.format, args.out_dir.is_some(), args.time) {
                 eprintln!("Error processing {}: {}", input_path.display(), e);
                 std::process::exit(1);
             }
         } else {
-            eprintln!("Error: The file is not a PNG image.");
+            eprintln!("Error: The file is not one of the supported image formats: {}.", args.formats.join(", "));
             std::process::exit(1);
         }
     } else {
@@ -0,0 +1,105 @@
+use crate::contour::{Contour, ThresholdField};
+use std::collections::HashMap;
+
+/// A crossing of the isoline with the line between two neighbouring pixel centers.
+/// `Right` is the edge from `(x, y)` to `(x + 1, y)`, `Down` the edge from `(x, y)` to `(x, y + 1)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Edge {
+    Right(i64, i64),
+    Down(i64, i64),
+}
+
+/// Traces every isoline between pixels above and pixels at or below `threshold`.
+///
+/// Pixels outside the image count as background, so every loop is closed.
+/// The shape is always on the same side of a loop, so outer boundaries and holes wind in opposite directions.
+/// At a saddle, where two diagonally opposite pixels are inside, the average of the four pixels decides
+/// whether the two pixels are connected.
+pub(crate) fn trace_isolines<F: ThresholdField + ?Sized>(image: &F, threshold: u8) -> Vec<Contour> {
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    let value = |x: i64, y: i64| -> f32 {
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return 0.0;
+        }
+        image.value_at(x as u32, y as u32) as f32
+    };
+    // Halfway between the last value counted as background and the first counted as inside.
+    let level = threshold as f32 + 0.5;
+
+    let mut next: HashMap<Edge, Edge> = HashMap::new();
+    let mut points: HashMap<Edge, [f32; 2]> = HashMap::new();
+
+    for y in -1..height {
+        for x in -1..width {
+            // The corners and the edges between them, clockwise from the top left.
+            let corners = [[x, y], [x + 1, y], [x + 1, y + 1], [x, y + 1]];
+            let edges = [Edge::Right(x, y), Edge::Down(x + 1, y), Edge::Right(x, y + 1), Edge::Down(x, y)];
+            let values = corners.map(|[cx, cy]| value(cx, cy));
+            let inside = values.map(|v| v > level);
+
+            // Crossings in clockwise order, each either entering or leaving the shape.
+            let mut crossings: Vec<(Edge, bool)> = vec![];
+            for (a, edge) in edges.into_iter().enumerate() {
+                let b = (a + 1) % 4;
+                if inside[a] == inside[b] {
+                    continue;
+                }
+
+                let t = (level - values[a]) / (values[b] - values[a]);
+                let [ax, ay] = corners[a];
+                let [bx, by] = corners[b];
+                points.entry(edge).or_insert([
+                    ax as f32 + (bx - ax) as f32 * t,
+                    ay as f32 + (by - ay) as f32 * t,
+                ]);
+                crossings.push((edge, inside[b]));
+            }
+
+            // Rotate so the first crossing enters the shape, giving enter, leave, enter, leave.
+            if let Some(first_entry) = crossings.iter().position(|(_, enters)| *enters) {
+                crossings.rotate_left(first_entry);
+            }
+
+            match crossings.as_slice() {
+                [(e0, _), (x0, _)] => {
+                    next.insert(*e0, *x0);
+                }
+                [(e0, _), (x0, _), (e1, _), (x1, _)] => {
+                    let center_inside = values.iter().sum::<f32>() / 4.0 > level;
+                    if center_inside {
+                        next.insert(*e0, *x1);
+                        next.insert(*e1, *x0);
+                    } else {
+                        next.insert(*e0, *x0);
+                        next.insert(*e1, *x1);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut contours: Vec<Contour> = vec![];
+    // Iterating over the sorted starting edges makes the order of the loops deterministic.
+    let mut starts: Vec<Edge> = next.keys().copied().collect();
+    starts.sort_by_key(|edge| match *edge {
+        Edge::Right(x, y) => (y, x, 0),
+        Edge::Down(x, y) => (y, x, 1),
+    });
+
+    for start in starts {
+        if !next.contains_key(&start) {
+            continue;
+        }
+
+        let mut contour = Contour::new();
+        let mut current = start;
+        while let Some(following) = next.remove(&current) {
+            contour.push(points[&current]);
+            current = following;
+        }
+        contours.push(contour);
+    }
+
+    contours
+}
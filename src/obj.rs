@@ -0,0 +1,468 @@
+use crate::{ExportOptions, MaterialParams, Mesh, MeshError, Surface};
+use obj_exporter::{Primitive, TVertex, VTNIndex, Vertex};
+#[cfg(feature = "io")]
+use std::fs::File;
+use std::io::{BufRead, Write};
+#[cfg(feature = "io")]
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// Saves a mesh to a OBJ file.
+///
+/// The coordinates are rounded to the 6 decimals of `ExportOptions::default()`, rather than written with the full
+/// precision of earlier versions, which keeps the files small and stable. `save_mesh_to_file_with_options` writes
+/// them with a different number of decimals.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to save.
+/// * `file_path` - The file path to save the mesh to. Has to end with `.obj`.
+///
+/// # Returns
+///
+/// A `Result` which is `Ok` if the mesh was saved successfully, or an `Err` containing a `MeshError`.
+#[cfg(feature = "io")]
+pub fn save_mesh_to_file(mesh: Mesh, file_path: impl AsRef<Path>) -> Result<(), MeshError> {
+    save_mesh_to_file_with_materials(mesh, file_path, MaterialParams::default())
+}
+
+/// Same as `save_mesh_to_file`, but with a solid color instead of the texture on the back or the sides,
+/// or a different texture file, as given by `materials`.
+#[cfg(feature = "io")]
+pub fn save_mesh_to_file_with_materials(mesh: Mesh, file_path: impl AsRef<Path>, materials: MaterialParams) -> Result<(), MeshError> {
+    save_mesh_to_file_with_options(mesh, file_path, materials, ExportOptions::default())
+}
+
+/// Same as `save_mesh_to_file_with_materials`, but with the coordinates written with `export.decimals` decimals.
+/// `export.write_texture` is not used, as there's no image to write.
+#[cfg(feature = "io")]
+pub fn save_mesh_to_file_with_options(
+    mesh: Mesh,
+    file_path: impl AsRef<Path>,
+    materials: MaterialParams,
+    export: ExportOptions)
+    -> Result<(), MeshError> {
+
+    save_obj(file_path.as_ref(), materials, |writer, mtl_name| write_mesh_obj_with_decimals(&mesh, writer, mtl_name, export.decimals))
+}
+
+/// Same as `save_mesh_to_file_with_materials`, but every mesh is saved as a separate object, named `island_0`,
+/// `island_1` and so on, so they can be selected on their own after importing. The objects share the materials.
+/// `Mesh::split_islands` splits a mesh into one mesh per shape. The coordinates are written with `export.decimals`
+/// decimals, like `save_mesh_to_file_with_options` does.
+#[cfg(feature = "io")]
+pub fn save_meshes_to_file(
+    meshes: &[Mesh],
+    file_path: impl AsRef<Path>,
+    materials: MaterialParams,
+    export: &ExportOptions)
+    -> Result<(), MeshError> {
+
+    save_obj(file_path.as_ref(), materials, |writer, mtl_name| write_meshes_obj(meshes, writer, mtl_name, export))
+}
+
+/// Writes the MTL file of `materials` next to `file_path`, and the OBJ file with `write_obj`,
+/// which gets the file name of the MTL file.
+#[cfg(feature = "io")]
+fn save_obj(
+    file_path: &Path,
+    materials: MaterialParams,
+    write_obj: impl FnOnce(&mut BufWriter<File>, &str) -> Result<(), MeshError>)
+    -> Result<(), MeshError> {
+
+    if file_path.extension().and_then(|s| s.to_str()) != Some("obj") {
+        return Err(MeshError::InvalidPath);
+    }
+
+    // Only the extension is replaced, so folders with `.obj` in their name are left alone.
+    let mtl_file_path = file_path.with_extension("mtl");
+    let png_file_path = file_path.with_extension("png");
+
+    let mtl_filename = mtl_file_path
+    .file_name()
+    .and_then(|s| s.to_str())
+    .unwrap_or("material.mtl");
+
+    let png_filename = materials.texture_path.as_deref().unwrap_or_else(|| png_file_path
+    .file_name()
+    .and_then(|s| s.to_str())
+    .unwrap_or("material.png"));
+
+    let mut mtl_file = File::create(&mtl_file_path)?;
+    write_mtl(&mut mtl_file, &materials, png_filename)?;
+
+    let mut obj_file = BufWriter::new(File::create(file_path)?);
+    write_obj(&mut obj_file, mtl_filename)?;
+    obj_file.flush()?;
+
+    Ok(())
+}
+
+/// Loads a mesh from an OBJ file, such as one saved by `save_mesh_to_file`.
+///
+/// Faces with more than three corners are split into a fan of triangles. The `front`, `back` and `side` materials
+/// give the surfaces of the triangles, and triangles with any other material are counted as the front.
+/// Vertex colors are read if every vertex has them. Objects, groups and the material library are ignored,
+/// so all objects are loaded into one mesh.
+///
+/// # Arguments
+///
+/// * `file_path` - The file path of the OBJ file.
+///
+/// # Returns
+///
+/// A `Result` containing the mesh, or an `Err` containing a `MeshError` if the file can't be read or parsed.
+#[cfg(feature = "io")]
+pub fn load_mesh_from_obj(file_path: impl AsRef<Path>) -> Result<Mesh, MeshError> {
+    read_mesh_obj(BufReader::new(File::open(file_path)?))
+}
+
+/// Same as `load_mesh_from_obj`, but reads the OBJ text from a reader.
+pub fn read_mesh_obj<R: BufRead>(reader: R) -> Result<Mesh, MeshError> {
+    let mut mesh = Mesh{vertices: vec![], triangles: vec![], uv_vertices: vec![], normals: vec![], surfaces: vec![], colors: vec![], quads: vec![]};
+    let mut surface = Surface::Front;
+    let mut all_colored = true;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let invalid = || MeshError::InvalidObj { line: i + 1 };
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else { continue };
+
+        let values = |tokens: std::str::SplitWhitespace| tokens.map(|t| t.parse::<f64>()).collect::<Result<Vec<f64>, _>>();
+        match keyword {
+            "v" => {
+                let values = values(tokens).map_err(|_| invalid())?;
+                let [x, y, z] = first_three(&values).ok_or_else(invalid)?;
+                mesh.vertices.push(Vertex{x, y, z});
+                match &values[3..] {
+                    [r, g, b, ..] => mesh.colors.push([*r, *g, *b].map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8)),
+                    _ => all_colored = false,
+                }
+            }
+            "vt" => {
+                let values = values(tokens).map_err(|_| invalid())?;
+                let u = *values.first().ok_or_else(invalid)?;
+                mesh.uv_vertices.push(TVertex{u, v: values.get(1).copied().unwrap_or(0.0), w: values.get(2).copied().unwrap_or(0.0)});
+            }
+            "vn" => {
+                let values = values(tokens).map_err(|_| invalid())?;
+                let [x, y, z] = first_three(&values).ok_or_else(invalid)?;
+                mesh.normals.push(Vertex{x, y, z});
+            }
+            "usemtl" => {
+                surface = match tokens.next() {
+                    Some("back") => Surface::Back,
+                    Some("side") => Surface::Side,
+                    _ => Surface::Front,
+                };
+            }
+            "f" | "l" | "p" => {
+                let counts = [mesh.vertices.len(), mesh.uv_vertices.len(), mesh.normals.len()];
+                let corners = tokens.map(|t| parse_vtn(t, counts)).collect::<Option<Vec<VTNIndex>>>().ok_or_else(invalid)?;
+                let primitives: Vec<Primitive> = match (keyword, corners.as_slice()) {
+                    ("f", [c0, rest @ ..]) if rest.len() >= 2 => rest.windows(2).map(|w| Primitive::Triangle(*c0, w[0], w[1])).collect(),
+                    ("l", [_, _, ..]) => corners.windows(2).map(|w| Primitive::Line(w[0], w[1])).collect(),
+                    ("p", [_, ..]) => corners.iter().map(|c| Primitive::Point(*c)).collect(),
+                    _ => return Err(invalid()),
+                };
+                if keyword == "f" && corners.len() == 4 {
+                    mesh.quads.resize(mesh.triangles.len(), false);
+                    mesh.quads.push(true);
+                }
+                mesh.surfaces.extend(std::iter::repeat_n(surface, primitives.len()));
+                mesh.triangles.extend(primitives);
+            }
+            _ => {}
+        }
+    }
+
+    if !all_colored {
+        mesh.colors.clear();
+    }
+    Ok(mesh)
+}
+
+fn first_three(values: &[f64]) -> Option<[f64; 3]> {
+    values.get(..3)?.try_into().ok()
+}
+
+/// Parses a `v`, `v/t`, `v/t/n` or `v//n` face corner into 0-based indices. Negative indices count back from
+/// the end of the `counts` vertices, uvs and normals read so far. Returns `None` if an index is beyond them.
+fn parse_vtn(token: &str, counts: [usize; 3]) -> Option<VTNIndex> {
+    let index = |part: &str, count: usize| -> Option<usize> {
+        match part.parse::<i64>().ok()? {
+            0 => None,
+            i if i > 0 => (i as usize <= count).then(|| i as usize - 1),
+            i => count.checked_sub(i.unsigned_abs() as usize),
+        }
+    };
+
+    let mut parts = token.split('/');
+    let v = index(parts.next()?, counts[0])?;
+    let t = match parts.next() {
+        None | Some("") => None,
+        Some(t) => Some(index(t, counts[1])?),
+    };
+    let n = match parts.next() {
+        None | Some("") => None,
+        Some(n) => Some(index(n, counts[2])?),
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((v, t, n))
+}
+
+/// Writes a mesh as OBJ text to a writer.
+///
+/// The output references the material library `mtl_name`, and uses its `front`, `back` and `side` materials
+/// for the triangles of the corresponding `Surface`.
+/// The object is named after the file stem of `mtl_name`.
+/// If the mesh has vertex colors, they are appended to the vertices as `v x y z r g b`, which MeshLab and Blender read.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to write.
+/// * `writer` - The writer to write the OBJ text to.
+/// * `mtl_name` - The file name of the material library, relative to the OBJ file.
+///
+/// # Returns
+///
+/// A `Result` which is `Ok` if the mesh was written successfully, or an `Err` containing a `MeshError`.
+pub fn write_mesh_obj<W: Write>(mesh: &Mesh, writer: &mut W, mtl_name: &str) -> Result<(), MeshError> {
+    write_mesh_obj_with_decimals(mesh, writer, mtl_name, ExportOptions::default().decimals)
+}
+
+/// Same as `write_mesh_obj`, but with the coordinates written with `decimals` decimals.
+pub(crate) fn write_mesh_obj_with_decimals<W: Write>(mesh: &Mesh, writer: &mut W, mtl_name: &str, decimals: usize) -> Result<(), MeshError> {
+    writeln!(writer, "mtllib {}", mtl_name)?;
+    write_object(writer, object_name(mtl_name), mesh, [0; 3], decimals)
+}
+
+/// Writes the material library of `materials`, with the surfaces that aren't given a color textured with `png_filename`.
+pub(crate) fn write_mtl<W: Write>(writer: &mut W, materials: &MaterialParams, png_filename: &str) -> Result<(), MeshError> {
+    for (surface, color) in [(Surface::Front, None), (Surface::Back, materials.back_color), (Surface::Side, materials.side_color)] {
+        writeln!(writer, "newmtl {}", material_name(surface))?;
+        match color {
+            Some([r, g, b]) => writeln!(writer, "Kd {:.6} {:.6} {:.6}", r, g, b)?,
+            None => writeln!(writer, "map_Kd {}", png_filename)?,
+        }
+        if let Some([r, g, b]) = materials.ambient {
+            writeln!(writer, "Ka {:.6} {:.6} {:.6}", r, g, b)?;
+        }
+        if let Some([r, g, b]) = materials.specular {
+            writeln!(writer, "Ks {:.6} {:.6} {:.6}", r, g, b)?;
+        }
+        if let Some(shininess) = materials.shininess {
+            writeln!(writer, "Ns {:.6}", shininess)?;
+        }
+    }
+    Ok(())
+}
+
+fn object_name(mtl_name: &str) -> &str {
+    Path::new(mtl_name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("default")
+}
+
+/// Same as `write_mesh_obj`, but every mesh is written as a separate object, named `island_0`, `island_1` and so on,
+/// and the coordinates are written with `export.decimals` decimals.
+pub fn write_meshes_obj<W: Write>(meshes: &[Mesh], writer: &mut W, mtl_name: &str, export: &ExportOptions) -> Result<(), MeshError> {
+    writeln!(writer, "mtllib {}", mtl_name)?;
+
+    // OBJ indices count from the start of the file, not from the start of the object.
+    let mut offset = [0; 3];
+    for (i, mesh) in meshes.iter().enumerate() {
+        write_object(writer, &format!("island_{}", i), mesh, offset, export.decimals)?;
+        offset = [offset[0] + mesh.vertices.len(), offset[1] + mesh.uv_vertices.len(), offset[2] + mesh.normals.len()];
+    }
+
+    Ok(())
+}
+
+/// Writes a mesh as an object, its vertex, uv and normal indices offset by `offset`,
+/// and its coordinates with `decimals` decimals.
+fn write_object<W: Write>(writer: &mut W, name: &str, mesh: &Mesh, offset: [usize; 3], decimals: usize) -> Result<(), MeshError> {
+    writeln!(writer, "o {}", name)?;
+
+    let has_colors = mesh.colors.len() == mesh.vertices.len();
+    for (i, v) in mesh.vertices.iter().enumerate() {
+        match has_colors {
+            true => {
+                let [r, g, b] = mesh.colors[i].map(|c| c as f32 / 255.0);
+                writeln!(writer, "v {:.*} {:.*} {:.*} {:.6} {:.6} {:.6}", decimals, v.x, decimals, v.y, decimals, v.z, r, g, b)?
+            }
+            false => writeln!(writer, "v {:.*} {:.*} {:.*}", decimals, v.x, decimals, v.y, decimals, v.z)?,
+        }
+    }
+    for uv in &mesh.uv_vertices {
+        if uv.w == 0.0 {
+            writeln!(writer, "vt {:.*} {:.*}", decimals, uv.u, decimals, uv.v)?;
+        } else {
+            writeln!(writer, "vt {:.*} {:.*} {:.*}", decimals, uv.u, decimals, uv.v, decimals, uv.w)?;
+        }
+    }
+    for n in &mesh.normals {
+        writeln!(writer, "vn {:.*} {:.*} {:.*}", decimals, n.x, decimals, n.y, decimals, n.z)?;
+    }
+
+    for surface in [Surface::Front, Surface::Back, Surface::Side] {
+        let mut triangles = mesh.triangles.iter().enumerate()
+            .filter(|(i, _)| mesh.surfaces.get(*i).copied().unwrap_or(Surface::Front) == surface)
+            .peekable();
+        if triangles.peek().is_none() {
+            continue;
+        }
+
+        writeln!(writer, "usemtl {}", material_name(surface))?;
+        while let Some((i, triangle)) = triangles.next() {
+            let quad = match (triangle, triangles.peek()) {
+                (Primitive::Triangle(a0, a1, a2), Some((j, Primitive::Triangle(b0, b1, b2))))
+                    if *j == i + 1 && mesh.quads.get(i) == Some(&true) => quad_corners([*a0, *a1, *a2], [*b0, *b1, *b2]),
+                _ => None,
+            };
+            let Some(corners) = quad else {
+                write_primitive(writer, triangle, offset)?;
+                continue;
+            };
+
+            triangles.next();
+            write!(writer, "f")?;
+            for corner in corners {
+                write_vtn(writer, corner, offset)?;
+            }
+            writeln!(writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The corners of the quad made of two triangles that share the corners of an edge, or `None` if they don't.
+/// For the triangles `(c0, c1, c2)` and `(c0, c2, c3)` that a quad face is read as, this is `(c0, c1, c2, c3)`.
+fn quad_corners(a: [VTNIndex; 3], b: [VTNIndex; 3]) -> Option<[VTNIndex; 4]> {
+    (0..3).find_map(|i| {
+        let [u, x, y] = [a[i], a[(i + 1) % 3], a[(i + 2) % 3]];
+        let j = (0..3).find(|j| b[*j] == y && b[(j + 1) % 3] == x)?;
+        let w = b[(j + 2) % 3];
+        (w != u).then_some([y, u, x, w])
+    })
+}
+
+fn material_name(surface: Surface) -> &'static str {
+    match surface {
+        Surface::Front => "front",
+        Surface::Back => "back",
+        Surface::Side => "side",
+    }
+}
+
+fn write_primitive<W: Write>(writer: &mut W, primitive: &Primitive, offset: [usize; 3]) -> Result<(), MeshError> {
+    match *primitive {
+        Primitive::Point(vtn) => {
+            write!(writer, "p")?;
+            write_vtn(writer, vtn, offset)?;
+        }
+        Primitive::Line(vtn0, vtn1) => {
+            write!(writer, "l")?;
+            write_vtn(writer, vtn0, offset)?;
+            write_vtn(writer, vtn1, offset)?;
+        }
+        Primitive::Triangle(vtn0, vtn1, vtn2) => {
+            write!(writer, "f")?;
+            write_vtn(writer, vtn0, offset)?;
+            write_vtn(writer, vtn1, offset)?;
+            write_vtn(writer, vtn2, offset)?;
+        }
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+// OBJ indices are 1-based.
+fn write_vtn<W: Write>(writer: &mut W, (v, t, n): VTNIndex, [v_offset, t_offset, n_offset]: [usize; 3]) -> Result<(), MeshError> {
+    let vtn = (v + v_offset, t.map(|t| t + t_offset), n.map(|n| n + n_offset));
+    match vtn {
+        (v, None, None) => write!(writer, " {}", v + 1)?,
+        (v, Some(t), None) => write!(writer, " {}/{}", v + 1, t + 1)?,
+        (v, Some(t), Some(n)) => write!(writer, " {}/{}/{}", v + 1, t + 1, n + 1)?,
+        (v, None, Some(n)) => write!(writer, " {}//{}", v + 1, n + 1)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two triangles, one on the front with uvs and one on the side without, and a line.
+    fn small_mesh() -> Mesh {
+        let vertex = |x, y, z| Vertex{x, y, z};
+        Mesh{
+            vertices: vec![vertex(0.0, 0.0, 0.0), vertex(1.0, 0.0, 0.0), vertex(0.0, 1.0, 0.0), vertex(0.0, 0.0, -0.5)],
+            triangles: vec![
+                Primitive::Triangle((0, Some(0), Some(0)), (1, Some(1), Some(0)), (2, Some(2), Some(0))),
+                Primitive::Line((0, Some(0), None), (2, Some(2), None)),
+                Primitive::Triangle((0, None, Some(1)), (3, None, Some(1)), (1, None, Some(1))),
+            ],
+            uv_vertices: vec![TVertex{u: 0.0, v: 0.0, w: 0.0}, TVertex{u: 1.0, v: 0.0, w: 0.0}, TVertex{u: 0.0, v: 1.0, w: 0.0}],
+            normals: vec![vertex(0.0, 0.0, 1.0), vertex(0.0, -1.0, 0.0)],
+            surfaces: vec![Surface::Front, Surface::Front, Surface::Side],
+            colors: vec![],
+            quads: vec![],
+        }
+    }
+
+    #[test]
+    fn written_mesh_reads_back_the_same() {
+        let mesh = small_mesh();
+        let mut obj = vec![];
+        write_mesh_obj(&mesh, &mut obj, "mesh.mtl").unwrap();
+        let loaded = read_mesh_obj(obj.as_slice()).unwrap();
+
+        let positions = |vertices: &[Vertex]| vertices.iter().map(|v| [v.x, v.y, v.z]).collect::<Vec<_>>();
+        assert_eq!(positions(&loaded.vertices), positions(&mesh.vertices));
+        assert_eq!(positions(&loaded.normals), positions(&mesh.normals));
+        let uvs = |uvs: &[TVertex]| uvs.iter().map(|t| [t.u, t.v, t.w]).collect::<Vec<_>>();
+        assert_eq!(uvs(&loaded.uv_vertices), uvs(&mesh.uv_vertices));
+        assert_eq!(loaded.surfaces, mesh.surfaces);
+        assert_eq!(loaded.triangles, mesh.triangles);
+    }
+
+    #[test]
+    fn face_with_missing_vertices_is_invalid() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nf 999 1000 1001\n";
+        assert!(matches!(read_mesh_obj(obj.as_bytes()), Err(MeshError::InvalidObj { line: 5 })));
+
+        let obj = "v 0 0 0\nv 1 0 0\nvt 0 0\nf 1/1 2/2 1/1\n";
+        assert!(matches!(read_mesh_obj(obj.as_bytes()), Err(MeshError::InvalidObj { line: 4 })));
+    }
+
+    #[cfg(feature = "io")]
+    #[test]
+    fn sidecar_paths_change_only_the_extension() {
+        let dir = std::env::temp_dir().join(format!("image_to_mesh_{}.objects", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let result = save_mesh_to_file(small_mesh(), dir.join("out.obj"));
+        let (obj, mtl) = (std::fs::read_to_string(dir.join("out.obj")), std::fs::read_to_string(dir.join("out.mtl")));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        result.unwrap();
+        assert!(obj.unwrap().starts_with("mtllib out.mtl\n"));
+        assert!(mtl.unwrap().contains("map_Kd out.png\n"));
+    }
+
+    #[test]
+    fn meshes_are_written_with_the_given_decimals() {
+        let mut obj = vec![];
+        write_meshes_obj(&[small_mesh(), small_mesh()], &mut obj, "mesh.mtl", &ExportOptions { decimals: 2, ..Default::default() }).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+
+        assert!(obj.lines().any(|line| line == "v 0.00 0.00 -0.50"), "{}", obj);
+        assert!(obj.contains("o island_1\n"));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::flatten::FlatMesh;
+use crate::{Mesh, MeshError};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Saves a mesh to an ASCII PLY file with per-vertex normals.
+///
+/// PLY has a single index per vertex, while the triangles of a `Mesh` reference positions, uvs and
+/// normals through separate indices, so a position can be used with more than one normal or uv, e.g.
+/// along the texture seams that `Mesh::weld_seams` keeps. Every distinct index triple used by a
+/// triangle corner is therefore written as its own PLY vertex, and the faces index into that list.
+/// This keeps positions and normals in sync, at the cost of duplicating positions along the seams.
+/// The normals are left out if any triangle corner has none, such as when `Params::include_normals` is off.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to save.
+/// * `file_path` - The file path to save the mesh to.
+///
+/// # Returns
+///
+/// A `Result` which is `Ok` if the mesh was saved successfully, or an `Err` containing a `MeshError`.
+pub fn save_mesh_to_ply(mesh: &Mesh, file_path: impl AsRef<Path>) -> Result<(), MeshError> {
+    let flat = FlatMesh::from_mesh(mesh)?;
+
+    let mut file = BufWriter::new(File::create(file_path)?);
+
+    writeln!(file, "ply")?;
+    writeln!(file, "format ascii 1.0")?;
+    writeln!(file, "comment generated by image_to_mesh")?;
+    writeln!(file, "element vertex {}", flat.positions.len())?;
+    writeln!(file, "property float x")?;
+    writeln!(file, "property float y")?;
+    writeln!(file, "property float z")?;
+    let has_normals = flat.has_normals && !mesh.normals.is_empty();
+    if has_normals {
+        writeln!(file, "property float nx")?;
+        writeln!(file, "property float ny")?;
+        writeln!(file, "property float nz")?;
+    }
+    writeln!(file, "element face {}", flat.indices.len() / 3)?;
+    writeln!(file, "property list uchar int vertex_indices")?;
+    writeln!(file, "end_header")?;
+
+    for (p, n) in flat.positions.iter().zip(&flat.normals) {
+        match has_normals {
+            true => writeln!(file, "{} {} {} {} {} {}", p[0], p[1], p[2], n[0], n[1], n[2])?,
+            false => writeln!(file, "{} {} {}", p[0], p[1], p[2])?,
+        }
+    }
+
+    for face in flat.indices.chunks(3) {
+        writeln!(file, "3 {} {} {}", face[0], face[1], face[2])?;
+    }
+
+    file.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Surface;
+    use obj_exporter::{Primitive, Vertex};
+
+    #[test]
+    fn mesh_without_normals_has_no_normal_properties() {
+        let vertices = vec![Vertex{x: 0.0, y: 0.0, z: 0.0}, Vertex{x: 1.0, y: 0.0, z: 0.0}, Vertex{x: 0.0, y: 1.0, z: 0.0}];
+        let triangles = vec![Primitive::Triangle((0, None, None), (1, None, None), (2, None, None))];
+        let mesh = Mesh{vertices, triangles, uv_vertices: vec![], normals: vec![], surfaces: vec![Surface::Front], colors: vec![], quads: vec![]};
+
+        let path = std::env::temp_dir().join(format!("image_to_mesh_{}_no_normals.ply", std::process::id()));
+        let result = save_mesh_to_ply(&mesh, &path);
+        let ply = std::fs::read_to_string(&path);
+        let _ = std::fs::remove_file(&path);
+
+        result.unwrap();
+        let ply = ply.unwrap();
+        assert!(!ply.contains("property float nx"), "{}", ply);
+        assert!(ply.contains("end_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n"), "{}", ply);
+    }
+}
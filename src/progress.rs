@@ -0,0 +1,35 @@
+use std::fmt;
+
+/// The steps of creating a mesh, reported to the progress callback of `create_mesh_from_image_with_progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// Computing the distance field of the shape has started.
+    SdfStarted,
+    /// The distance field is done, and tracing the contours in it has started.
+    TracingStarted,
+    /// The contours have been traced, giving `points` points in total.
+    ContourTraced { points: usize },
+    /// The tracer gave up on a contour after `points` points without getting back to where it started, and
+    /// the contour was closed where it was, see `contour::Params::close_unclosed_contours`.
+    ContourNotClosed { points: usize },
+    /// Smoothing iteration `iteration` of `total` is done, counting from 1.
+    Smoothing { iteration: u32, total: u32 },
+    /// Triangulating the contours and building the mesh has started.
+    Triangulating,
+    /// The mesh is finished.
+    Done,
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Progress::SdfStarted => write!(f, "computing distance field"),
+            Progress::TracingStarted => write!(f, "tracing contours"),
+            Progress::ContourTraced { points } => write!(f, "traced contour with {} points", points),
+            Progress::ContourNotClosed { points } => write!(f, "warning: closed a contour the tracer didn't finish, after {} points", points),
+            Progress::Smoothing { iteration, total } => write!(f, "smoothing {}/{}", iteration, total),
+            Progress::Triangulating => write!(f, "triangulating"),
+            Progress::Done => write!(f, "done"),
+        }
+    }
+}
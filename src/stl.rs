@@ -0,0 +1,125 @@
+use crate::{Mesh, MeshError};
+use obj_exporter::{Primitive, Vertex};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Saves a mesh to a STL file.
+///
+/// STL stores one normal per face, so the normals are computed from the vertex positions of each
+/// triangle rather than taken from `mesh.normals`. Triangles are written in the same winding order
+/// as in the mesh.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to save.
+/// * `file_path` - The file path to save the mesh to.
+/// * `ascii` - Whether to write ASCII STL instead of binary STL.
+///
+/// # Returns
+///
+/// A `Result` which is `Ok` if the mesh was saved successfully, or an `Err` containing a `MeshError`,
+/// `MeshError::IndexOutOfBounds` if a triangle refers to a vertex that doesn't exist.
+pub fn save_mesh_to_stl(mesh: Mesh, file_path: impl AsRef<Path>, ascii: bool) -> Result<(), MeshError> {
+    let mut triangles: Vec<[&Vertex; 3]> = Vec::with_capacity(mesh.triangles.len());
+    for (i, triangle) in mesh.triangles.iter().enumerate() {
+        let Primitive::Triangle(c0, c1, c2) = triangle else { continue };
+        let [v0, v1, v2] = [c0.0, c1.0, c2.0].map(|v| mesh.vertices.get(v));
+        let (Some(v0), Some(v1), Some(v2)) = (v0, v1, v2) else {
+            return Err(MeshError::IndexOutOfBounds { triangle: i });
+        };
+        triangles.push([v0, v1, v2]);
+    }
+
+    let mut file = BufWriter::new(File::create(file_path)?);
+
+    if ascii {
+        write_ascii_stl(&mut file, &triangles)?;
+    } else {
+        write_binary_stl(&mut file, &triangles)?;
+    }
+
+    file.flush()?;
+    Ok(())
+}
+
+fn write_binary_stl<W: Write>(writer: &mut W, triangles: &[[&Vertex; 3]]) -> Result<(), MeshError> {
+    let mut header = [0u8; 80];
+    let title = b"image_to_mesh";
+    header[..title.len()].copy_from_slice(title);
+    writer.write_all(&header)?;
+
+    let triangle_count = u32::try_from(triangles.len()).map_err(|_| MeshError::MeshTooLarge)?;
+    writer.write_all(&triangle_count.to_le_bytes())?;
+
+    for triangle in triangles {
+        let normal = face_normal(triangle);
+        for value in normal {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for vertex in triangle {
+            for value in [vertex.x as f32, vertex.y as f32, vertex.z as f32] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        // Attribute byte count, unused.
+        writer.write_all(&[0u8; 2])?;
+    }
+
+    Ok(())
+}
+
+fn write_ascii_stl<W: Write>(writer: &mut W, triangles: &[[&Vertex; 3]]) -> Result<(), MeshError> {
+    writeln!(writer, "solid image_to_mesh")?;
+
+    for triangle in triangles {
+        let normal = face_normal(triangle);
+        writeln!(writer, "  facet normal {:e} {:e} {:e}", normal[0], normal[1], normal[2])?;
+        writeln!(writer, "    outer loop")?;
+        for vertex in triangle {
+            writeln!(writer, "      vertex {:e} {:e} {:e}", vertex.x as f32, vertex.y as f32, vertex.z as f32)?;
+        }
+        writeln!(writer, "    endloop")?;
+        writeln!(writer, "  endfacet")?;
+    }
+
+    writeln!(writer, "endsolid image_to_mesh")?;
+    Ok(())
+}
+
+fn face_normal(triangle: &[&Vertex; 3]) -> [f32; 3] {
+    let [v0, v1, v2] = triangle;
+    let a = [v1.x - v0.x, v1.y - v0.y, v1.z - v0.z];
+    let b = [v2.x - v0.x, v2.y - v0.y, v2.z - v0.z];
+    let n = [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+    let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+
+    // Degenerate triangles get a zero normal, which slicers recompute themselves.
+    if len == 0.0 {
+        return [0.0, 0.0, 0.0];
+    }
+
+    [(n[0] / len) as f32, (n[1] / len) as f32, (n[2] / len) as f32]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Surface;
+
+    #[test]
+    fn missing_vertex_is_an_error() {
+        let vertices = vec![Vertex{x: 0.0, y: 0.0, z: 0.0}, Vertex{x: 1.0, y: 0.0, z: 0.0}, Vertex{x: 0.0, y: 1.0, z: 0.0}];
+        let triangles = vec![
+            Primitive::Triangle((0, None, None), (1, None, None), (2, None, None)),
+            Primitive::Triangle((0, None, None), (2, None, None), (3, None, None)),
+        ];
+        let mesh = Mesh{vertices, triangles, uv_vertices: vec![], normals: vec![], surfaces: vec![Surface::Front; 2], colors: vec![], quads: vec![]};
+
+        let path = std::env::temp_dir().join(format!("image_to_mesh_{}_missing_vertex.stl", std::process::id()));
+        let result = save_mesh_to_stl(mesh, &path, false);
+
+        assert!(matches!(result, Err(MeshError::IndexOutOfBounds { triangle: 1 })), "{:?}", result);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,107 @@
+use crate::Mesh;
+use obj_exporter::Primitive;
+
+// UV triangles with a smaller (doubled) area than this don't define a tangent space.
+const DEGENERATE_UV_AREA: f64 = 1e-12;
+
+/// Computes a tangent for every normal of the mesh, for normal mapping.
+///
+/// Tangents are accumulated per triangle with Lengyel's method and orthogonalized against the normal.
+/// The returned tangents are indexed like `mesh.normals`, as that is how triangle corners share shading data.
+/// `w` is the handedness, so the bitangent is `cross(normal, tangent) * w`.
+///
+/// The side walls reuse the cap UVs, so their UV triangles collapse to a line. Such triangles fall back to
+/// the direction of the edge along which the UVs change the most, and a normal without any usable triangle
+/// gets an arbitrary tangent perpendicular to it.
+pub fn compute_tangents(mesh: &Mesh) -> Vec<[f32; 4]> {
+    let n_normals = mesh.normals.len();
+    let mut tangents = vec![[0.0f64; 3]; n_normals];
+    let mut bitangents = vec![[0.0f64; 3]; n_normals];
+    let mut fallbacks = vec![[0.0f64; 3]; n_normals];
+
+    for triangle in &mesh.triangles {
+        let Primitive::Triangle(c0, c1, c2) = triangle else { continue };
+        let corners = [*c0, *c1, *c2];
+
+        let (Some(uvs), Some(normals)) = (
+            corners.iter().map(|(_, t, _)| t.filter(|t| *t < mesh.uv_vertices.len())).collect::<Option<Vec<_>>>(),
+            corners.iter().map(|(_, _, n)| n.filter(|n| *n < n_normals)).collect::<Option<Vec<_>>>(),
+        ) else { continue };
+
+        let p = corners.map(|(v, _, _)| {
+            let v = &mesh.vertices[v];
+            [v.x, v.y, v.z]
+        });
+        let uv = [0, 1, 2].map(|i| {
+            let t = &mesh.uv_vertices[uvs[i]];
+            [t.u, t.v]
+        });
+
+        let e1 = sub(p[1], p[0]);
+        let e2 = sub(p[2], p[0]);
+        let (du1, dv1) = (uv[1][0] - uv[0][0], uv[1][1] - uv[0][1]);
+        let (du2, dv2) = (uv[2][0] - uv[0][0], uv[2][1] - uv[0][1]);
+        let r = du1 * dv2 - du2 * dv1;
+
+        if r.abs() < DEGENERATE_UV_AREA {
+            let edges = [(e1, du1, dv1), (e2, du2, dv2), (sub(p[2], p[1]), du2 - du1, dv2 - dv1)];
+            let (edge, du, _) = edges.into_iter()
+                .max_by(|a, b| (a.1 * a.1 + a.2 * a.2).total_cmp(&(b.1 * b.1 + b.2 * b.2)))
+                .unwrap();
+            let edge = if du < 0.0 { scale(edge, -1.0) } else { edge };
+            for n in &normals {
+                fallbacks[*n] = add(fallbacks[*n], edge);
+            }
+            continue;
+        }
+
+        let sdir = scale(sub(scale(e1, dv2), scale(e2, dv1)), 1.0 / r);
+        let tdir = scale(sub(scale(e2, du1), scale(e1, du2)), 1.0 / r);
+        for n in &normals {
+            tangents[*n] = add(tangents[*n], sdir);
+            bitangents[*n] = add(bitangents[*n], tdir);
+        }
+    }
+
+    mesh.normals.iter().enumerate().map(|(i, n)| {
+        let n = [n.x, n.y, n.z];
+        let t = if dot(tangents[i], tangents[i]) > 0.0 { tangents[i] } else { fallbacks[i] };
+
+        // Gram-Schmidt orthogonalization against the normal.
+        let t = normalize(sub(t, scale(n, dot(n, t)))).unwrap_or_else(|| perpendicular(n));
+        let w = if dot(cross(n, t), bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+
+        [t[0] as f32, t[1] as f32, t[2] as f32, w]
+    }).collect()
+}
+
+/// Returns a unit vector perpendicular to `n`, or the x axis if `n` is zero.
+fn perpendicular(n: [f64; 3]) -> [f64; 3] {
+    let axis = if n[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+    normalize(cross(n, axis)).unwrap_or([1.0, 0.0, 0.0])
+}
+
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f64; 3], s: f64) -> [f64; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(a: [f64; 3]) -> Option<[f64; 3]> {
+    let len = dot(a, a).sqrt();
+    (len > 1e-12 && len.is_finite()).then(|| scale(a, 1.0 / len))
+}
@@ -0,0 +1,94 @@
+use crate::zip::write_zip;
+use crate::{Mesh, MeshError, WELD_EPSILON};
+use obj_exporter::Primitive;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const CONTENT_TYPES: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+    r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+    r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+    r#"<Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml"/>"#,
+    r#"</Types>"#,
+);
+
+const RELATIONSHIPS: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    r#"<Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>"#,
+    r#"</Relationships>"#,
+);
+
+/// Saves a mesh to a 3MF file, the package format most slicers prefer for 3D printing.
+///
+/// The model is written in millimeters, so a vertex coordinate of 1.0 is 1 mm. `Params::world_scale` sizes the
+/// mesh in these units. 3MF requires a closed mesh where neighbouring triangles share their vertices, so
+/// coincident vertices are merged, and triangles that collapse to a line or point when merged are left out.
+/// Only the geometry is written, without uvs, normals or materials.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to save.
+/// * `file_path` - The file path to save the mesh to.
+///
+/// # Returns
+///
+/// A `Result` which is `Ok` if the mesh was saved successfully, or an `Err` containing a `MeshError`,
+/// `MeshError::IndexOutOfBounds` if a triangle refers to a vertex that doesn't exist.
+pub fn save_mesh_to_3mf(mesh: &Mesh, file_path: impl AsRef<Path>) -> Result<(), MeshError> {
+    let model = model_xml(mesh)?;
+
+    let mut file = BufWriter::new(File::create(file_path)?);
+    write_zip(&mut file, &[
+        ("[Content_Types].xml", CONTENT_TYPES.as_bytes()),
+        ("_rels/.rels", RELATIONSHIPS.as_bytes()),
+        ("3D/3dmodel.model", model.as_bytes()),
+    ])?;
+
+    file.flush()?;
+    Ok(())
+}
+
+/// The 3D model part of the package, or `MeshError::IndexOutOfBounds` if a triangle refers to a vertex that
+/// doesn't exist.
+fn model_xml(mesh: &Mesh) -> Result<String, MeshError> {
+    let key = |x: f64| (x / WELD_EPSILON).round() as i64;
+
+    let mut lookup: HashMap<(i64, i64, i64), usize> = HashMap::new();
+    let mut vertices = String::new();
+    let welded: Vec<usize> = mesh.vertices.iter().map(|v| {
+        let n_vertices = lookup.len();
+        *lookup.entry((key(v.x), key(v.y), key(v.z))).or_insert_with(|| {
+            let _ = write!(vertices, r#"<vertex x="{}" y="{}" z="{}"/>"#, v.x as f32, v.y as f32, v.z as f32);
+            n_vertices
+        })
+    }).collect();
+
+    let mut triangles = String::new();
+    for (i, triangle) in mesh.triangles.iter().enumerate() {
+        let Primitive::Triangle(c0, c1, c2) = triangle else { continue };
+        let [v1, v2, v3] = [c0.0, c1.0, c2.0].map(|v| welded.get(v).copied());
+        let (Some(v1), Some(v2), Some(v3)) = (v1, v2, v3) else {
+            return Err(MeshError::IndexOutOfBounds { triangle: i });
+        };
+        if v1 == v2 || v2 == v3 || v3 == v1 {
+            continue;
+        }
+        let _ = write!(triangles, r#"<triangle v1="{}" v2="{}" v3="{}"/>"#, v1, v2, v3);
+    }
+
+    Ok(format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<model unit="millimeter" xml:lang="en-US" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">"#,
+            r#"<resources><object id="1" type="model"><mesh><vertices>{}</vertices><triangles>{}</triangles></mesh></object></resources>"#,
+            r#"<build><item objectid="1"/></build>"#,
+            r#"</model>"#,
+        ),
+        vertices,
+        triangles
+    ))
+}
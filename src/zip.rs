@@ -0,0 +1,105 @@
+use crate::obj::{write_mesh_obj_with_decimals, write_mtl};
+use crate::{ExportOptions, MaterialParams, Mesh, MeshError};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// 1980-01-01 00:00, the earliest date a zip file can store.
+const DOS_DATE: u16 = (1 << 5) | 1;
+const DOS_TIME: u16 = 0;
+
+/// Saves a mesh as a zip archive holding `model.obj`, `model.mtl` and `texture.png`, so it can be passed around
+/// as a single file. The OBJ file refers to the material library, and the material library to the texture, by
+/// these names, so the archive can be unpacked anywhere. The materials are `MaterialParams::default()`.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to save.
+/// * `texture_png` - The PNG encoded texture, saved as `texture.png`.
+/// * `file_path` - The file path to save the zip archive to.
+///
+/// # Returns
+///
+/// A `Result` which is `Ok` if the mesh was saved successfully, or an `Err` containing a `MeshError`.
+pub fn save_mesh_to_zip(mesh: &Mesh, texture_png: &[u8], file_path: impl AsRef<Path>) -> Result<(), MeshError> {
+    let mut obj = vec![];
+    write_mesh_obj_with_decimals(mesh, &mut obj, "model.mtl", ExportOptions::default().decimals)?;
+    let mut mtl = vec![];
+    write_mtl(&mut mtl, &MaterialParams::default(), "texture.png")?;
+
+    let mut file = BufWriter::new(File::create(file_path)?);
+    write_zip(&mut file, &[
+        ("model.obj", obj.as_slice()),
+        ("model.mtl", mtl.as_slice()),
+        ("texture.png", texture_png),
+    ])?;
+
+    file.flush()?;
+    Ok(())
+}
+
+/// Writes the files as an uncompressed zip archive.
+pub(crate) fn write_zip<W: Write>(writer: &mut W, files: &[(&str, &[u8])]) -> Result<(), MeshError> {
+    let mut central_directory: Vec<u8> = vec![];
+    let mut offset: usize = 0;
+
+    for (name, data) in files {
+        let crc = crc32(data);
+        let size = u32::try_from(data.len()).map_err(|_| MeshError::MeshTooLarge)?;
+        let local_offset = u32::try_from(offset).map_err(|_| MeshError::MeshTooLarge)?;
+        let name_length = name.len() as u16;
+
+        let mut header: Vec<u8> = vec![];
+        header.extend_from_slice(&0x0403_4B50u32.to_le_bytes());
+        header.extend_from_slice(&20u16.to_le_bytes()); // Version needed to extract.
+        header.extend_from_slice(&0u16.to_le_bytes()); // Flags.
+        header.extend_from_slice(&0u16.to_le_bytes()); // Stored, without compression.
+        header.extend_from_slice(&DOS_TIME.to_le_bytes());
+        header.extend_from_slice(&DOS_DATE.to_le_bytes());
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&name_length.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // Extra field length.
+        header.extend_from_slice(name.as_bytes());
+
+        writer.write_all(&header)?;
+        writer.write_all(data)?;
+        offset += header.len() + data.len();
+
+        central_directory.extend_from_slice(&0x0201_4B50u32.to_le_bytes());
+        central_directory.extend_from_slice(&20u16.to_le_bytes()); // Version made by.
+        central_directory.extend_from_slice(&header[4..30]);
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // File comment length.
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // Disk number.
+        central_directory.extend_from_slice(&0u16.to_le_bytes()); // Internal attributes.
+        central_directory.extend_from_slice(&0u32.to_le_bytes()); // External attributes.
+        central_directory.extend_from_slice(&local_offset.to_le_bytes());
+        central_directory.extend_from_slice(name.as_bytes());
+    }
+
+    let n_files = files.len() as u16;
+    writer.write_all(&central_directory)?;
+    writer.write_all(&0x0605_4B50u32.to_le_bytes())?;
+    writer.write_all(&0u16.to_le_bytes())?; // Disk number.
+    writer.write_all(&0u16.to_le_bytes())?; // Disk with the central directory.
+    writer.write_all(&n_files.to_le_bytes())?;
+    writer.write_all(&n_files.to_le_bytes())?;
+    writer.write_all(&u32::try_from(central_directory.len()).map_err(|_| MeshError::MeshTooLarge)?.to_le_bytes())?;
+    writer.write_all(&u32::try_from(offset).map_err(|_| MeshError::MeshTooLarge)?.to_le_bytes())?;
+    writer.write_all(&0u16.to_le_bytes())?; // Comment length.
+
+    Ok(())
+}
+
+/// The CRC-32 checksum used by zip files.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}